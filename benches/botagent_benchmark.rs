@@ -1,7 +1,10 @@
 // benches/botagent_benchmark.rs
 
-use botagent::is_bot;
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use botagent::{is_bot, pattern, BotDetector};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use pcre2::bytes::RegexBuilder;
+use std::io::Write;
+use tempfile::NamedTempFile;
 
 fn benchmark_botagent(c: &mut Criterion) {
     let json_path = "src/patterns.json";
@@ -15,7 +18,102 @@ fn benchmark_botagent(c: &mut Criterion) {
             black_box(result);
         })
     });
+
+    let caseless = BotDetector::from_json_path(json_path).unwrap();
+    let lowercase = BotDetector::builder()
+        .json_path(json_path)
+        .precompute_lowercase(true)
+        .build()
+        .unwrap();
+
+    c.bench_function("detector_is_bot_caseless", |b| {
+        b.iter(|| black_box(caseless.is_bot(black_box(user_agent))))
+    });
+
+    c.bench_function("detector_is_bot_precompute_lowercase", |b| {
+        b.iter(|| black_box(lowercase.is_bot(black_box(user_agent))))
+    });
+}
+
+// patterns starting with distinctive bytes, rejected by the prefilter on browser user agents
+fn benchmark_prefilter(c: &mut Criterion) {
+    let patterns: Vec<String> = ["^curl/", "^wget", "^python", "^java/", r"\[bot\]", "@"]
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    let user_agent = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36";
+
+    let prefiltered = BotDetector::from_patterns(&patterns).unwrap();
+    let regex_only = BotDetector::from_regex(
+        RegexBuilder::new()
+            .caseless(true)
+            .build(&patterns.join("|"))
+            .unwrap(),
+    );
+
+    c.bench_function("is_bot_prefilter", |b| {
+        b.iter(|| black_box(prefiltered.is_bot(black_box(user_agent))))
+    });
+
+    c.bench_function("is_bot_regex_only", |b| {
+        b.iter(|| black_box(regex_only.is_bot(black_box(user_agent))))
+    });
+}
+
+// compare with `cargo bench --features parallel` to measure the parallel startup
+fn benchmark_compile_all(c: &mut Criterion) {
+    let patterns = pattern::read_patterns("src/patterns.json").unwrap();
+    let many_patterns: Vec<&str> = patterns
+        .iter()
+        .map(String::as_str)
+        .cycle()
+        .take(patterns.len() * 50)
+        .collect();
+    let mut builder = RegexBuilder::new();
+    builder.caseless(true);
+
+    c.bench_function("compile_all", |b| {
+        b.iter(|| black_box(pattern::compile_all(black_box(&many_patterns), &builder)))
+    });
+}
+
+// construction cost only, matching is measured by the benchmarks above
+fn benchmark_construction(c: &mut Criterion) {
+    let patterns = pattern::read_patterns("src/patterns.json").unwrap();
+    let sizes = [
+        ("small", 10),
+        ("medium", patterns.len()),
+        ("large", patterns.len() * 10),
+    ];
+
+    let mut group = c.benchmark_group("construction");
+
+    for (size, count) in sizes {
+        let set: Vec<String> = patterns.iter().cycle().take(count).cloned().collect();
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(serde_json::to_string(&set).unwrap().as_bytes())
+            .unwrap();
+        let json_path = file.path().to_str().unwrap();
+
+        group.bench_with_input(
+            BenchmarkId::new("from_json_path", size),
+            json_path,
+            |b, path| b.iter(|| black_box(BotDetector::from_json_path(black_box(path)).unwrap())),
+        );
+
+        group.bench_with_input(BenchmarkId::new("from_patterns", size), &set, |b, set| {
+            b.iter(|| black_box(BotDetector::from_patterns(black_box(set)).unwrap()))
+        });
+    }
+
+    group.finish();
 }
 
-criterion_group!(benches, benchmark_botagent);
+criterion_group!(
+    benches,
+    benchmark_botagent,
+    benchmark_prefilter,
+    benchmark_compile_all,
+    benchmark_construction
+);
 criterion_main!(benches);
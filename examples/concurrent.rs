@@ -0,0 +1,36 @@
+// examples/concurrent.rs
+//
+// A single compiled `BotDetector` shared across worker threads through an `Arc`, without any
+// locking. Run with `cargo run --example concurrent`.
+
+use botagent::BotDetector;
+use std::sync::Arc;
+use std::thread;
+
+fn main() {
+    let detector = Arc::new(BotDetector::from_json_path("src/patterns.json").unwrap());
+
+    let user_agents = [
+        "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)",
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36",
+        "curl/7.68.0",
+    ];
+
+    let workers: Vec<_> = user_agents
+        .into_iter()
+        .map(|user_agent| {
+            let detector = Arc::clone(&detector);
+
+            thread::spawn(move || (user_agent, detector.classify(user_agent)))
+        })
+        .collect();
+
+    for worker in workers {
+        let (user_agent, info) = worker.join().unwrap();
+
+        match info {
+            Some(info) => println!("bot ({}): {user_agent}", info.matched_text),
+            None => println!("not a bot: {user_agent}"),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Code Generation Module
+//!
+//! This module provides helpers for build scripts, baking a validated pattern set into the
+//! binary so that startup skips reading and parsing the patterns file.
+
+use crate::detector::BotDetector;
+use crate::errors::BotDetectorError;
+use std::fs;
+
+/// Load a JSON patterns file and write a Rust source file exposing its merged pattern.
+///
+/// The patterns are loaded and compiled as by [`BotDetector::from_json_path`], so an invalid
+/// file fails the build instead of the program's startup. The generated file declares
+/// `pub const MERGED_PATTERN: &str`, meant to be included with `include!` and handed to
+/// [`BotDetector::from_static_pattern`].
+///
+/// # Arguments
+///
+/// * `json_path` - Path to the JSON file containing bot patterns.
+/// * `out` - Path of the Rust source file to be written, usually under `OUT_DIR`.
+///
+/// # Errors
+///
+/// Returns a `BotDetectorError` if the patterns file cannot be read or parsed, if the patterns
+/// cannot be compiled, or if the source file cannot be written.
+///
+/// # Example
+///
+/// In `build.rs`:
+///
+/// ```no_run
+/// let out = format!("{}/patterns.rs", std::env::var("OUT_DIR").unwrap());
+/// botagent::codegen::emit_merged_pattern("patterns.json", &out).unwrap();
+/// println!("cargo:rerun-if-changed=patterns.json");
+/// ```
+///
+/// And in the crate:
+///
+/// ```ignore
+/// include!(concat!(env!("OUT_DIR"), "/patterns.rs"));
+///
+/// let detector = botagent::BotDetector::from_static_pattern(MERGED_PATTERN).unwrap();
+/// ```
+pub fn emit_merged_pattern(json_path: &str, out: &str) -> Result<(), BotDetectorError> {
+    let detector = BotDetector::from_json_path(json_path)?;
+    let pattern = detector.merged_pattern().unwrap_or_default();
+
+    fs::write(out, merged_pattern_source(json_path, pattern))?;
+
+    Ok(())
+}
+
+/// Returns the Rust source declaring the merged pattern.
+fn merged_pattern_source(json_path: &str, pattern: &str) -> String {
+    // `Debug` escapes the strings into valid Rust string literals
+    format!(
+        "/// The merged bot pattern generated from {json_path:?}.\n\
+         pub const MERGED_PATTERN: &str = {pattern:?};\n"
+    )
+}
+
+#[cfg(test)]
+mod features {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_emit_merged_pattern() {
+        let json = NamedTempFile::new().unwrap();
+        fs::write(json.path(), r#"["Googlebot", "curl\\/\\d", "say \"hi\""]"#).unwrap();
+        let out = NamedTempFile::new().unwrap();
+
+        emit_merged_pattern(json.path().to_str().unwrap(), out.path().to_str().unwrap()).unwrap();
+        let source = fs::read_to_string(out.path()).unwrap();
+        assert!(source.ends_with(
+            "pub const MERGED_PATTERN: &str = \"Googlebot|curl\\\\/\\\\d|say \\\"hi\\\"\";\n"
+        ));
+
+        let detector = BotDetector::from_static_pattern("Googlebot|curl\\/\\d|say \"hi\"").unwrap();
+        assert!(detector.is_bot("curl/7.68.0"));
+        assert!(detector.is_bot("they say \"hi\""));
+        assert!(!detector.is_bot("Mozilla/5.0"));
+
+        fs::write(json.path(), r#"["(unclosed"]"#).unwrap();
+        assert!(
+            emit_merged_pattern(json.path().to_str().unwrap(), out.path().to_str().unwrap())
+                .is_err()
+        );
+        assert!(BotDetector::from_static_pattern("(unclosed").is_err());
+    }
+}
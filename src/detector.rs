@@ -0,0 +1,6041 @@
+//! Bot Detector Module
+//!
+//! This module provides [`BotDetector`], an instance-based alternative to the free functions.
+//! A detector owns its compiled patterns, so several detectors with different pattern sets can
+//! live side by side and no global state is involved.
+
+use crate::errors::BotDetectorError;
+#[cfg(feature = "metrics")]
+use crate::metrics::Counters;
+use crate::pattern::{self, PatternEntry};
+use crate::prefilter::Prefilter;
+use pcre2::bytes::{Match, Regex, RegexBuilder};
+use pcre2::{Error as Pcre2Error, ErrorKind as Pcre2ErrorKind};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{BufRead, Read};
+use std::sync::{Arc, LazyLock, OnceLock};
+use std::time::{Duration, Instant};
+
+/// A compiled set of bot patterns.
+///
+/// The patterns are retained alongside the merged regex so the set can be extended at runtime.
+/// Each pattern is also compiled on its own, which is what allows a match of the merged regex to
+/// be attributed back to the pattern that produced it.
+///
+/// Clones share the same compiled regexes, so cloning a detector to hand it to several workers
+/// doesn't recompile anything. A clone extended with [`BotDetector::add_patterns`] gets regexes
+/// of its own, and clones of a detector built with [`BotDetectorBuilder::count_hits`] add to the
+/// same counts.
+#[derive(Debug, Clone)]
+pub struct BotDetector {
+    entries: Vec<PatternEntry>,
+    regex: Arc<Regex>,
+    // each pattern with its regex compiled on its own, in load order
+    compiled: Arc<Vec<(String, Regex)>>,
+    options: Options,
+    injected: bool,
+    exclusion: Option<Arc<Regex>>,
+    prefilter: Option<Prefilter>,
+    client_hints: Option<Box<BotDetector>>,
+    headless: Option<Arc<BotDetector>>,
+    dropped: Vec<usize>,
+    // compiled on first use by `classify_merged`, `None` if it failed to compile
+    marked: Arc<OnceLock<Option<MarkedRegex>>>,
+    // the merged regex compiled without JIT on the first JIT stack error, `None` if it failed
+    interpreted: Arc<OnceLock<Option<Regex>>>,
+    // `Some` for a detector built from the bundled pattern list
+    pattern_version: Option<&'static str>,
+    // `Some` for a detector built with `count_hits`
+    #[cfg(feature = "metrics")]
+    counters: Option<Arc<Counters>>,
+}
+
+/// The merged regex with every pattern wrapped in a capturing group, see
+/// [`BotDetector::classify_merged`].
+#[derive(Debug)]
+struct MarkedRegex {
+    regex: Regex,
+    // the number of the group wrapping each pattern, in load order
+    groups: Vec<usize>,
+}
+
+/// The result of classifying a user agent as a bot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BotInfo {
+    /// The pattern that matched the user agent.
+    pub pattern: String,
+
+    /// The name of the bot, if the matching entry carries one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// The category of the bot, if the matching entry carries one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+
+    /// The part of the user agent matched by the pattern.
+    pub matched_text: String,
+}
+
+impl From<Explanation> for BotInfo {
+    /// Keeps the explanation's fields but its span.
+    fn from(explanation: Explanation) -> Self {
+        Self {
+            pattern: explanation.pattern,
+            name: explanation.name,
+            category: explanation.category,
+            matched_text: explanation.matched_text,
+        }
+    }
+}
+
+impl fmt::Display for BotInfo {
+    /// Formats a one-line summary, e.g.
+    /// `Googlebot (category=crawler, pattern=googlebot) matched "Googlebot"`.
+    ///
+    /// Without a name, the pattern is used as the label, e.g. `curl matched "curl"`, and
+    /// missing fields are left out of the parentheses.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut details = Vec::new();
+
+        if let Some(category) = &self.category {
+            details.push(format!("category={category}"));
+        }
+
+        match &self.name {
+            Some(name) => {
+                details.push(format!("pattern={}", self.pattern));
+                write!(f, "{name}")?;
+            }
+            None => write!(f, "{}", self.pattern)?,
+        }
+
+        if !details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
+        }
+
+        write!(f, " matched {:?}", self.matched_text)
+    }
+}
+
+/// The three-valued outcome of [`BotDetector::classify_tri`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Classification {
+    /// The user agent matched a bot pattern.
+    Bot(BotInfo),
+
+    /// The user agent was checked and matched no bot pattern.
+    Human,
+
+    /// The user agent could not be checked: it is empty or blank, or matching failed.
+    Unknown,
+}
+
+/// Counts of the bots found in a log, see [`BotDetector::summarize_log`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LogSummary {
+    /// The number of lines read, including those that could not be checked.
+    pub total_lines: u64,
+
+    /// The number of lines classified as a bot.
+    pub bot_lines: u64,
+
+    /// The number of bot lines per bot name, or per pattern for entries without a name.
+    pub by_name: HashMap<String, u64>,
+}
+
+/// How many sample user agents a pattern matches, see [`BotDetector::pattern_stats`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PatternStats {
+    /// The pattern.
+    pub pattern: String,
+
+    /// The number of samples the pattern matches on its own.
+    pub matches: usize,
+}
+
+/// What a detector was loaded with, see [`BotDetector::load_report`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LoadReport {
+    /// The number of patterns compiled into the detector.
+    pub patterns: usize,
+
+    /// The indexes of the entries dropped because their pattern is blank.
+    pub dropped: Vec<usize>,
+
+    /// The version of the bundled pattern list, if the detector was built from it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+/// How a detector treats pcre2 errors raised while matching, e.g. hitting the match limit, see
+/// [`BotDetectorBuilder::on_match_error`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// The user agent is treated as not a bot.
+    #[default]
+    AsNotBot,
+
+    /// The user agent is treated as a bot, since input the engine fails on is suspicious.
+    AsBot,
+
+    /// The error is returned by [`BotDetector::try_is_bot`] and [`BotDetector::try_classify`];
+    /// the methods that can't return it treat the user agent as not a bot.
+    Propagate,
+}
+
+/// The newline convention the patterns are compiled with, see [`BotDetectorBuilder::newline`].
+///
+/// It decides where `$` matches, and where `^` and `$` match in multi-line mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Newline {
+    /// A carriage return.
+    Cr,
+
+    /// A linefeed.
+    Lf,
+
+    /// A carriage return followed by a linefeed.
+    Crlf,
+
+    /// Any Unicode line ending, including carriage returns, linefeeds and `\r\n`.
+    Any,
+}
+
+impl Newline {
+    /// Returns the pcre2 verb selecting the convention at the start of a pattern.
+    fn verb(self) -> &'static str {
+        match self {
+            Newline::Cr => "(*CR)",
+            Newline::Lf => "(*LF)",
+            Newline::Crlf => "(*CRLF)",
+            Newline::Any => "(*ANY)",
+        }
+    }
+}
+
+/// Hints from outside the user agent, used by [`BotDetector::classify_with_signals`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Signals {
+    /// The request comes from a datacenter IP address.
+    pub datacenter_ip: bool,
+
+    /// The request carries none of the cookies a browser session would have.
+    pub missing_cookie: bool,
+}
+
+/// A human-facing rationale for why a user agent was classified as a bot.
+///
+/// Serializes to a flat object, e.g.
+/// `{"pattern":"googlebot","name":"Googlebot","matched_text":"Googlebot","span":[25,34]}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Explanation {
+    /// The pattern that matched the user agent.
+    pub pattern: String,
+
+    /// The name of the bot, if the matching entry carries one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// The category of the bot, if the matching entry carries one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+
+    /// The part of the user agent matched by the pattern.
+    pub matched_text: String,
+
+    /// The byte range `(start, end)` of the match in the user agent.
+    pub span: (usize, usize),
+}
+
+/// The key under which [`BotDetector::category_histogram`] tallies bots without a category.
+pub const UNCATEGORIZED: &str = "uncategorized";
+
+/// Browser engine tokens used by [`BotDetector::looks_automated`] unless overridden with
+/// [`BotDetectorBuilder::engine_tokens`].
+///
+/// Real browsers advertise their rendering engine in the user agent, so a user agent carrying
+/// none of these is often an automated client.
+pub const DEFAULT_ENGINE_TOKENS: &[&str] = &["Gecko", "WebKit", "Trident", "Presto"];
+
+/// User agents of this length in bytes or longer are never flagged by
+/// [`BotDetector::is_minimal_client`].
+///
+/// Browsers send user agents well over 64 bytes, while HTTP libraries and command line tools
+/// usually send a short `tool/version` string.
+pub const MINIMAL_CLIENT_MAX_LEN: usize = 64;
+
+/// The default maximum length in bytes of a reported matched text, see
+/// [`BotDetectorBuilder::max_match_len`].
+pub const DEFAULT_MAX_MATCH_LEN: usize = 256;
+
+/// Appended to a matched text cut at [`BotDetectorBuilder::max_match_len`].
+pub const TRUNCATION_MARKER: &str = "\u{2026}";
+
+/// The bundled headless browser signatures, shipped as `src/headless.json`.
+const HEADLESS_PATTERNS_JSON: &str = include_str!("headless.json");
+
+/// The detector for the bundled headless browser signatures, shared by every detector that
+/// doesn't override them.
+static BUNDLED_HEADLESS: LazyLock<Arc<BotDetector>> = LazyLock::new(|| {
+    let patterns: Vec<String> =
+        serde_json::from_str(HEADLESS_PATTERNS_JSON).expect("Invalid bundled headless patterns");
+    let detector = Options::default()
+        .nested(&patterns)
+        .expect("Invalid bundled headless patterns");
+
+    Arc::new(detector)
+});
+
+/// The bundled bot patterns, shipped as `src/patterns.json`, see
+/// [`BotDetector::with_default_patterns`].
+const DEFAULT_PATTERNS_JSON: &str = include_str!("patterns.json");
+
+/// The bundled names of well-known good bots, shipped as `src/good_bots.json`.
+const GOOD_BOTS_JSON: &str = include_str!("good_bots.json");
+
+/// The bundled good bot names, shared by every detector that doesn't override them.
+static BUNDLED_GOOD_BOTS: LazyLock<Vec<String>> =
+    LazyLock::new(|| serde_json::from_str(GOOD_BOTS_JSON).expect("Invalid bundled good bot names"));
+
+/// Compile and query options shared by the builder and the detector it produces.
+#[derive(Debug, Clone)]
+#[allow(clippy::struct_excessive_bools)]
+struct Options {
+    precompute_lowercase: bool,
+    ascii_lowercase: bool,
+    raw: bool,
+    dotall: bool,
+    multiline: bool,
+    jit: bool,
+    strip_quotes: bool,
+    strip_mozilla_prefix: bool,
+    ignore_url_segments: bool,
+    sort_tokens: bool,
+    engine_tokens: Vec<String>,
+    exclusions: Vec<String>,
+    client_hints: Vec<String>,
+    // `None` for the bundled signatures
+    headless: Option<Vec<String>>,
+    // `None` for the bundled names
+    good_bots: Option<Vec<String>>,
+    max_patterns: Option<usize>,
+    max_match_len: Option<usize>,
+    on_match_error: ErrorPolicy,
+    require_utf8: bool,
+    empty_ua_is_bot: bool,
+    // `None` for pcre2's default
+    newline: Option<Newline>,
+    #[cfg(feature = "metrics")]
+    count_hits: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            precompute_lowercase: false,
+            ascii_lowercase: false,
+            raw: false,
+            dotall: false,
+            multiline: false,
+            jit: false,
+            strip_quotes: false,
+            strip_mozilla_prefix: false,
+            ignore_url_segments: false,
+            sort_tokens: false,
+            engine_tokens: DEFAULT_ENGINE_TOKENS
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            exclusions: Vec::new(),
+            client_hints: Vec::new(),
+            headless: None,
+            good_bots: None,
+            max_patterns: None,
+            max_match_len: Some(DEFAULT_MAX_MATCH_LEN),
+            on_match_error: ErrorPolicy::AsNotBot,
+            require_utf8: false,
+            empty_ua_is_bot: false,
+            newline: None,
+            #[cfg(feature = "metrics")]
+            count_hits: false,
+        }
+    }
+}
+
+impl Options {
+    /// Whether the patterns are compiled with pcre2's `caseless` flag.
+    fn caseless(&self) -> bool {
+        !self.precompute_lowercase && !self.raw
+    }
+
+    /// Returns a `RegexBuilder` configured according to the options.
+    fn regex_builder(&self) -> RegexBuilder {
+        let mut builder = RegexBuilder::new();
+        builder
+            .caseless(self.caseless())
+            .dotall(self.dotall)
+            .multi_line(self.multiline)
+            .jit_if_available(self.jit);
+        builder
+    }
+
+    /// Returns what goes before a whole pattern handed to pcre2, selecting the newline
+    /// convention if one is set.
+    fn newline_verb(&self) -> &'static str {
+        self.newline.map_or("", Newline::verb)
+    }
+
+    /// Returns the pattern source as it should be handed to pcre2.
+    fn source<'a>(&self, pattern: &'a str) -> Cow<'a, str> {
+        if self.precompute_lowercase {
+            Cow::Owned(lowercase_pattern(pattern, self.ascii_lowercase))
+        } else {
+            Cow::Borrowed(pattern)
+        }
+    }
+
+    /// Compile each pattern on its own according to the options, failing on the first error.
+    fn compile_each(
+        &self,
+        entries: &[PatternEntry],
+    ) -> Result<Vec<(String, Regex)>, BotDetectorError> {
+        self.compile_each_results(entries).into_iter().collect()
+    }
+
+    /// Compile each pattern on its own according to the options, with one result per entry.
+    ///
+    /// Case-sensitive entries are compiled separately, without `caseless`.
+    fn compile_each_results(
+        &self,
+        entries: &[PatternEntry],
+    ) -> Vec<Result<(String, Regex), BotDetectorError>> {
+        let mut sensitive = Vec::new();
+        let mut caseless = Vec::new();
+        for entry in entries {
+            let mut source = self.source(&entry.pattern);
+            if self.newline.is_some() {
+                source = Cow::Owned(format!("{}{source}", self.newline_verb()));
+            }
+            if entry.case_sensitive {
+                sensitive.push(source);
+            } else {
+                caseless.push(source);
+            }
+        }
+
+        let mut sensitive_builder = self.regex_builder();
+        sensitive_builder.caseless(false);
+
+        let mut sensitive = pattern::compile_all(&sensitive, &sensitive_builder).into_iter();
+        let mut caseless = pattern::compile_all(&caseless, &self.regex_builder()).into_iter();
+
+        // both halves yield exactly one result per entry of their kind, in load order
+        entries
+            .iter()
+            .filter_map(|entry| {
+                let result = if entry.case_sensitive {
+                    sensitive.next()
+                } else {
+                    caseless.next()
+                };
+                Some(result?.map(|regex| (entry.pattern.clone(), regex)))
+            })
+            .collect()
+    }
+
+    /// Merge the patterns with `|` and compile them according to the options.
+    fn compile_merged(&self, entries: &[PatternEntry]) -> Result<Regex, BotDetectorError> {
+        let pattern_str = entries
+            .iter()
+            .map(|entry| self.merged_source(entry))
+            .collect::<Vec<_>>()
+            .join("|");
+        let pattern_str = format!(
+            "{}{}{pattern_str}",
+            self.newline_verb(),
+            dupnames_prefix(entries)
+        );
+
+        Ok(self.regex_builder().build(&pattern_str)?)
+    }
+
+    /// Merge the patterns like `compile_merged`, wrapping each of them in a capturing group.
+    ///
+    /// `compiled` holds the patterns compiled on their own, which gives the number of groups of
+    /// each and so the number of the group wrapping the next.
+    fn compile_marked(
+        &self,
+        entries: &[PatternEntry],
+        compiled: &[(String, Regex)],
+    ) -> Result<MarkedRegex, BotDetectorError> {
+        let mut groups = Vec::with_capacity(entries.len());
+        let mut next_group = 1;
+
+        for (_, regex) in compiled {
+            groups.push(next_group);
+            // `captures_len` counts the implicit group 0, which stands for the wrapping group
+            next_group += regex.captures_len();
+        }
+
+        let pattern_str = entries
+            .iter()
+            .map(|entry| format!("({})", self.merged_source(entry)))
+            .collect::<Vec<_>>()
+            .join("|");
+        let pattern_str = format!(
+            "{}{}{pattern_str}",
+            self.newline_verb(),
+            dupnames_prefix(entries)
+        );
+
+        Ok(MarkedRegex {
+            regex: self.regex_builder().build(&pattern_str)?,
+            groups,
+        })
+    }
+
+    /// Returns the source of the entry's branch in the merged regex.
+    ///
+    /// Case-sensitive entries are scoped with `(?-i:...)` so they keep their casing within the
+    /// caseless merged regex.
+    fn merged_source<'a>(&self, entry: &'a PatternEntry) -> Cow<'a, str> {
+        let source = self.source(&entry.pattern);
+
+        if entry.case_sensitive && self.caseless() {
+            Cow::Owned(format!("(?-i:{source})"))
+        } else {
+            source
+        }
+    }
+
+    /// Analyse the patterns for the prefilter used by `is_bot`.
+    fn prefilter(&self, entries: &[PatternEntry]) -> Option<Prefilter> {
+        // `^` also matches after a newline in multi-line mode
+        if self.multiline {
+            return None;
+        }
+
+        let sources: Vec<_> = entries
+            .iter()
+            .map(|entry| self.source(&entry.pattern))
+            .collect();
+
+        Prefilter::build(sources.iter().map(AsRef::as_ref), self.caseless())
+    }
+
+    /// Merge the exclusion patterns into a single regex, if there are any.
+    fn compile_exclusion(&self) -> Result<Option<Regex>, BotDetectorError> {
+        if self.exclusions.is_empty() {
+            return Ok(None);
+        }
+
+        let entries: Vec<_> = self
+            .exclusions
+            .iter()
+            .cloned()
+            .map(PatternEntry::from)
+            .collect();
+
+        Ok(Some(self.compile_merged(&entries)?))
+    }
+
+    /// Build the detector used for the `Sec-CH-UA` header, if there are client hint patterns.
+    fn compile_client_hints(&self) -> Result<Option<Box<BotDetector>>, BotDetectorError> {
+        if self.client_hints.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Box::new(self.nested(&self.client_hints)?)))
+    }
+
+    /// Build the detector used by `is_headless`, if there are headless patterns.
+    fn compile_headless(&self) -> Result<Option<Arc<BotDetector>>, BotDetectorError> {
+        match &self.headless {
+            None => Ok(Some(Arc::clone(&BUNDLED_HEADLESS))),
+            Some(patterns) if patterns.is_empty() => Ok(None),
+            Some(patterns) => Ok(Some(Arc::new(self.nested(patterns)?))),
+        }
+    }
+
+    /// Build a detector for a secondary pattern set, with the same compile options but no
+    /// secondary sets of its own.
+    fn nested(&self, patterns: &[String]) -> Result<BotDetector, BotDetectorError> {
+        let entries = patterns.iter().cloned().map(PatternEntry::from).collect();
+        let options = Options {
+            exclusions: Vec::new(),
+            client_hints: Vec::new(),
+            headless: Some(Vec::new()),
+            empty_ua_is_bot: false,
+            ..self.clone()
+        };
+
+        BotDetector::with_options(entries, options)
+    }
+
+    /// Returns the names of the bots allowed by `is_good_bot`.
+    fn good_bots(&self) -> &[String] {
+        self.good_bots.as_deref().unwrap_or(&BUNDLED_GOOD_BOTS)
+    }
+
+    /// Check the number of patterns against `max_patterns`.
+    fn check_count(&self, count: usize) -> Result<(), BotDetectorError> {
+        match self.max_patterns {
+            Some(limit) if count > limit => Err(BotDetectorError::TooManyPatterns { count, limit }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Convert matched bytes to the reported text, cut at `max_match_len`.
+    fn matched_text(&self, matched: &[u8]) -> String {
+        let mut text = String::from_utf8_lossy(matched).to_string();
+
+        if let Some(limit) = self.max_match_len {
+            if text.len() > limit {
+                let mut end = limit;
+                while !text.is_char_boundary(end) {
+                    end -= 1;
+                }
+                text.truncate(end);
+                text.push_str(TRUNCATION_MARKER);
+            }
+        }
+
+        text
+    }
+
+    /// Prepare the user agent for matching according to the options.
+    fn subject<'a>(&self, user_agent: &'a str) -> Cow<'a, str> {
+        let user_agent = if self.strip_quotes {
+            strip_quotes(user_agent)
+        } else {
+            user_agent
+        };
+        let user_agent = if self.strip_mozilla_prefix {
+            strip_mozilla_prefix(user_agent)
+        } else {
+            user_agent
+        };
+        let user_agent = if self.ignore_url_segments {
+            strip_urls(user_agent)
+        } else {
+            Cow::Borrowed(user_agent)
+        };
+        let user_agent = if self.sort_tokens {
+            Cow::Owned(sort_tokens(&user_agent))
+        } else {
+            user_agent
+        };
+
+        if self.precompute_lowercase && self.ascii_lowercase {
+            Cow::Owned(user_agent.to_ascii_lowercase())
+        } else if self.precompute_lowercase {
+            Cow::Owned(user_agent.to_lowercase())
+        } else {
+            user_agent
+        }
+    }
+}
+
+/// Split the user agent into tokens on whitespace, `;`, `,`, `(` and `)`, sort them
+/// ignoring ASCII case, and join them with single spaces.
+fn sort_tokens(user_agent: &str) -> String {
+    let mut tokens: Vec<&str> = user_agent
+        .split(|c: char| c.is_whitespace() || matches!(c, ';' | ',' | '(' | ')'))
+        .filter(|token| !token.is_empty())
+        .collect();
+    tokens.sort_by(|a, b| {
+        a.bytes()
+            .map(|byte| byte.to_ascii_lowercase())
+            .cmp(b.bytes().map(|byte| byte.to_ascii_lowercase()))
+    });
+
+    tokens.join(" ")
+}
+
+/// Remove the URLs from the user agent: each `http://` or `https://`, case-insensitive and
+/// optionally preceded by `+`, up to the next whitespace, `;` or `)`.
+fn strip_urls(user_agent: &str) -> Cow<'_, str> {
+    // ASCII lowercasing keeps byte offsets, so they apply to `user_agent` as well
+    let lowered = user_agent.to_ascii_lowercase();
+    let mut stripped = String::new();
+    let mut kept_from = 0;
+    let mut search_from = 0;
+
+    while let Some(found) = lowered[search_from..].find("http") {
+        let start = search_from + found;
+        let rest = &lowered[start..];
+        let scheme_len = if rest.starts_with("https://") {
+            "https://".len()
+        } else if rest.starts_with("http://") {
+            "http://".len()
+        } else {
+            search_from = start + "http".len();
+            continue;
+        };
+
+        let url_end = lowered[start + scheme_len..]
+            .find(|c: char| c.is_whitespace() || c == ';' || c == ')')
+            .map_or(lowered.len(), |end| start + scheme_len + end);
+        let url_start = if lowered[..start].ends_with('+') {
+            start - 1
+        } else {
+            start
+        };
+
+        stripped.push_str(&user_agent[kept_from..url_start]);
+        kept_from = url_end;
+        search_from = url_end;
+    }
+
+    if kept_from == 0 {
+        return Cow::Borrowed(user_agent);
+    }
+
+    stripped.push_str(&user_agent[kept_from..]);
+    Cow::Owned(stripped)
+}
+
+/// pcre2's `PCRE2_ERROR_JIT_STACKLIMIT`.
+const JIT_STACKLIMIT: i32 = -46;
+
+/// Whether the error reports that JIT matching ran out of JIT stack.
+fn is_jit_stack_error(error: &Pcre2Error) -> bool {
+    matches!(error.kind(), Pcre2ErrorKind::Match) && error.code() == JIT_STACKLIMIT
+}
+
+/// Convert a pcre2 match error to the error returned by the `try_` methods.
+fn match_error(error: Pcre2Error) -> BotDetectorError {
+    if is_jit_stack_error(&error) {
+        BotDetectorError::JitStack
+    } else {
+        BotDetectorError::Match(error)
+    }
+}
+
+/// The token browsers and most bots start their user agent with, see
+/// [`BotDetectorBuilder::strip_mozilla_prefix`].
+const MOZILLA_PREFIX: &str = "Mozilla/5.0 ";
+
+/// Check if the user agent is a single `tool/version` token, see
+/// [`BotDetector::is_minimal_client`].
+fn is_tool_version(user_agent: &str) -> bool {
+    let Some((tool, version)) = user_agent.split_once('/') else {
+        return false;
+    };
+
+    !tool.is_empty()
+        && tool
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+        && version.starts_with(|c: char| c.is_ascii_digit())
+        && !version.contains(|c: char| c.is_whitespace() || c == '/')
+}
+
+/// Remove a leading `Mozilla/5.0 `, matched ASCII case-insensitively.
+fn strip_mozilla_prefix(user_agent: &str) -> &str {
+    match user_agent.get(..MOZILLA_PREFIX.len()) {
+        Some(prefix) if prefix.eq_ignore_ascii_case(MOZILLA_PREFIX) => {
+            &user_agent[MOZILLA_PREFIX.len()..]
+        }
+        _ => user_agent,
+    }
+}
+
+/// Remove a single pair of matching double or single quotes surrounding the user agent.
+fn strip_quotes(user_agent: &str) -> &str {
+    ['"', '\'']
+        .into_iter()
+        .find_map(|quote| {
+            user_agent
+                .strip_prefix(quote)
+                .and_then(|rest| rest.strip_suffix(quote))
+        })
+        .unwrap_or(user_agent)
+}
+
+/// Returns `(?J)` if any of the patterns names a group, which lets several of them use the same
+/// name, e.g. `version`, once they are merged into a single regex.
+///
+/// Unnamed groups never need it; a false positive, e.g. an escaped `\(?<`, is harmless.
+fn dupnames_prefix(entries: &[PatternEntry]) -> &'static str {
+    let names_group = |pattern: &str| {
+        pattern.match_indices("(?").any(|(at, _)| {
+            let rest = &pattern[at + 2..];
+            rest.starts_with("P<")
+                || rest.starts_with('\'')
+                || (rest.starts_with('<') && !rest.starts_with("<=") && !rest.starts_with("<!"))
+        })
+    };
+
+    if entries.iter().any(|entry| names_group(&entry.pattern)) {
+        "(?J)"
+    } else {
+        ""
+    }
+}
+
+/// Lowercase a pattern while leaving the character following a `\` untouched, so escapes such
+/// as `\D`, `\W` or `\S` keep their meaning, and the names of verbs such as `(*UTF)`,
+/// `(*SKIP)` or `(*LIMIT_MATCH=1000)`, which pcre2 only accepts in uppercase. With `ascii`, only
+/// ASCII letters are lowercased.
+fn lowercase_pattern(pattern: &str, ascii: bool) -> String {
+    let mut lowered = String::with_capacity(pattern.len());
+    let mut escaped = false;
+    let mut chars = pattern.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if escaped {
+            lowered.push(c);
+            escaped = false;
+        } else if c == '(' && chars.peek() == Some(&'*') {
+            // the verb's name runs up to its argument, if any, or the closing parenthesis
+            lowered.push(c);
+            while let Some(c) = chars.next_if(|&c| c != ':' && c != ')') {
+                lowered.push(c);
+            }
+        } else {
+            escaped = c == '\\';
+            if ascii {
+                lowered.push(c.to_ascii_lowercase());
+            } else {
+                lowered.extend(c.to_lowercase());
+            }
+        }
+    }
+
+    lowered
+}
+
+/// A builder for configuring how a [`BotDetector`] compiles and matches its patterns.
+///
+/// # Example
+///
+/// ```rust
+/// # use botagent::BotDetectorBuilder;
+/// let detector = BotDetectorBuilder::new()
+///     .patterns(&["Googlebot".to_string()])
+///     .precompute_lowercase(true)
+///     .build()
+///     .unwrap();
+/// assert!(detector.is_bot("Mozilla/5.0 (compatible; GOOGLEBOT/2.1)"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BotDetectorBuilder {
+    source: Source,
+    recursive: bool,
+    assert_detects: Vec<String>,
+    options: Options,
+}
+
+/// Where the builder reads its patterns from.
+#[derive(Debug, Clone, Default)]
+enum Source {
+    #[default]
+    None,
+    Entries(Vec<PatternEntry>),
+    JsonPath(String),
+    Dir(String),
+}
+
+impl BotDetectorBuilder {
+    /// Create a builder with the default options.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build the detector from the given list of patterns.
+    #[must_use]
+    pub fn patterns(mut self, patterns: &[String]) -> Self {
+        self.source = Source::Entries(patterns.iter().cloned().map(PatternEntry::from).collect());
+        self
+    }
+
+    /// Build the detector from the given list of pattern entries.
+    #[must_use]
+    pub fn entries(mut self, entries: &[PatternEntry]) -> Self {
+        self.source = Source::Entries(entries.to_vec());
+        self
+    }
+
+    /// Build the detector from the patterns in the given JSON file.
+    #[must_use]
+    pub fn json_path(mut self, json_path: &str) -> Self {
+        self.source = Source::JsonPath(json_path.to_string());
+        self
+    }
+
+    /// Build the detector from the patterns of every `.json` file in the given directory, see
+    /// [`pattern::read_entries_from_dir`].
+    #[must_use]
+    pub fn dir(mut self, dir: &str) -> Self {
+        self.source = Source::Dir(dir.to_string());
+        self
+    }
+
+    /// Also read the JSON files of subdirectories, at any depth, when building from a
+    /// [`BotDetectorBuilder::dir`]. Defaults to `false`.
+    #[must_use]
+    pub fn recursive(mut self, yes: bool) -> Self {
+        self.recursive = yes;
+        self
+    }
+
+    /// Lowercase the patterns once at build time and every user agent at query time, instead of
+    /// compiling with pcre2's `caseless` flag.
+    ///
+    /// Caseless matching has a cost on every match; for pure-ASCII pattern sets lowercasing the
+    /// user agent up front is usually cheaper. The character following a `\` is never
+    /// lowercased, so escapes such as `\W` keep their meaning, and neither are the names of
+    /// verbs such as `(*UTF)` or `(*LIMIT_MATCH=1000)`.
+    ///
+    /// Lowercasing uses Rust's [`str::to_lowercase`], which applies the full Unicode lowercase
+    /// mapping regardless of the locale: `İ` becomes `i̇` and the Kelvin sign `K` becomes the
+    /// ASCII `k`, possibly changing the length of the user agent. pcre2's `caseless` flag, as
+    /// the detector compiles without UTF mode, only folds ASCII letters. The two are therefore
+    /// only equivalent for ASCII, and matched text is reported from the lowercased copy of the
+    /// user agent. Use [`BotDetectorBuilder::ascii_lowercase`] to only lowercase ASCII letters.
+    /// Defaults to `false`.
+    #[must_use]
+    pub fn precompute_lowercase(mut self, yes: bool) -> Self {
+        self.options.precompute_lowercase = yes;
+        self
+    }
+
+    /// With [`BotDetectorBuilder::precompute_lowercase`], lowercase with
+    /// [`str::to_ascii_lowercase`] instead of [`str::to_lowercase`].
+    ///
+    /// Only ASCII letters are lowercased, leaving every other character and the byte length of
+    /// the user agent unchanged, which is faster and folds exactly like the default `caseless`
+    /// matching. Non-ASCII letters in patterns and user agents then match case-sensitively.
+    /// Meant for pattern sets known to be ASCII. Defaults to `false`.
+    #[must_use]
+    pub fn ascii_lowercase(mut self, yes: bool) -> Self {
+        self.options.ascii_lowercase = yes;
+        self
+    }
+
+    /// Compile the patterns with pcre2's JIT compiler when it is available, which speeds up
+    /// matching at the cost of a slower build.
+    ///
+    /// JIT matching runs on a fixed-size stack, which deeply nested patterns can exhaust on long
+    /// user agents. Queries then retry without JIT, with the merged regex compiled on the first
+    /// such error and kept for the next ones, so results are the same as without JIT;
+    /// with [`ErrorPolicy::Propagate`], [`BotDetector::try_is_bot`] and
+    /// [`BotDetector::try_classify`] report the error instead. Defaults to `false`.
+    #[must_use]
+    pub fn jit(mut self, yes: bool) -> Self {
+        self.options.jit = yes;
+        self
+    }
+
+    /// Remove a single pair of surrounding double or single quotes from every user agent before
+    /// matching, as log pipelines often quote the user agent field.
+    ///
+    /// Only matching outermost quotes are stripped: `"Googlebot/2.1"` becomes `Googlebot/2.1`,
+    /// while internal quotes and unbalanced ones such as `"Googlebot/2.1` are left alone. Spans
+    /// and matched text refer to the stripped user agent. Defaults to `false`.
+    #[must_use]
+    pub fn strip_quotes(mut self, yes: bool) -> Self {
+        self.options.strip_quotes = yes;
+        self
+    }
+
+    /// Remove a leading `Mozilla/5.0 ` from every user agent before matching.
+    ///
+    /// Browsers and bots alike start their user agent with this token, so it tells them apart
+    /// no better than nothing and patterns can focus on what follows. The prefix is matched ASCII
+    /// case-insensitively, after [`BotDetectorBuilder::strip_quotes`], and must be followed by a
+    /// space; a user agent without it is left alone. A pattern relying on the prefix, e.g.
+    /// `^Mozilla/5\.0 \(compatible`, no longer matches. Spans and matched text refer to the
+    /// stripped user agent, so spans are `12` bytes short of the original string. Defaults to
+    /// `false`.
+    #[must_use]
+    pub fn strip_mozilla_prefix(mut self, yes: bool) -> Self {
+        self.options.strip_mozilla_prefix = yes;
+        self
+    }
+
+    /// Remove the URLs from every user agent before matching, so that e.g. the `bot` in
+    /// `+http://www.google.com/bot.html` doesn't count as a match.
+    ///
+    /// A URL starts at `http://` or `https://`, matched case-insensitively and including a
+    /// preceding `+`, and runs up to the next whitespace, `;` or `)`, or the end of the user
+    /// agent; the rest of the user agent is kept as is. Spans and matched text refer to the
+    /// stripped user agent. Defaults to `false`.
+    #[must_use]
+    pub fn ignore_url_segments(mut self, yes: bool) -> Self {
+        self.options.ignore_url_segments = yes;
+        self
+    }
+
+    /// Match every user agent with its tokens in sorted order, so that patterns can catch bots
+    /// reordering their tokens to evade detection.
+    ///
+    /// The user agent is split on whitespace, `;`, `,`, `(` and `)`, the tokens are sorted
+    /// ignoring ASCII case, and they are joined with single spaces: `Mozilla/5.0 (compatible;
+    /// Googlebot/2.1)` becomes `compatible Googlebot/2.1 Mozilla/5.0`. A pattern spanning
+    /// several tokens must list them in that order, separated by a space. This runs after
+    /// [`BotDetectorBuilder::strip_quotes`] and [`BotDetectorBuilder::ignore_url_segments`].
+    ///
+    /// Spans and matched text refer to the sorted user agent and don't correspond to the
+    /// original string. Meant for that narrow anti-evasion use case. Defaults to `false`.
+    #[must_use]
+    pub fn sort_tokens(mut self, yes: bool) -> Self {
+        self.options.sort_tokens = yes;
+        self
+    }
+
+    /// Compile the patterns without pcre2's `caseless` flag, so matching is case-sensitive
+    /// unless a pattern asks otherwise with an inline flag such as `(?i)` or `(?i:...)`.
+    ///
+    /// By default every pattern is compiled caseless, and inline flags override it within their
+    /// scope: `(?-i:Exact)` only matches `Exact` while the rest of the pattern stays caseless.
+    /// Since the patterns are merged with `|`, prefer the scoped `(?-i:...)` form: a bare
+    /// `(?-i)` also applies to the patterns that follow it in the merged regex.
+    ///
+    /// This doesn't affect [`BotDetectorBuilder::precompute_lowercase`], which still lowercases
+    /// the patterns and user agents. Defaults to `false`.
+    #[must_use]
+    pub fn raw(mut self, yes: bool) -> Self {
+        self.options.raw = yes;
+        self
+    }
+
+    /// Compile the patterns with pcre2's `dotall` flag, so `.` also matches newlines.
+    ///
+    /// The detector matches the whole user agent at once, so this only matters for user agents
+    /// containing newlines. Defaults to `false`.
+    #[must_use]
+    pub fn dotall(mut self, yes: bool) -> Self {
+        self.options.dotall = yes;
+        self
+    }
+
+    /// Compile the patterns with pcre2's `multi_line` flag, so `^` and `$` also match at the
+    /// start and end of every line of the user agent instead of only at its ends.
+    ///
+    /// Defaults to `false`.
+    #[must_use]
+    pub fn multiline(mut self, yes: bool) -> Self {
+        self.options.multiline = yes;
+        self
+    }
+
+    /// Compile the patterns with the given newline convention, which decides where `$` matches
+    /// and, with [`BotDetectorBuilder::multiline`], where every line starts and ends.
+    ///
+    /// With [`Newline::Crlf`] or [`Newline::Any`], a user agent sent with an embedded `\r\n`
+    /// is split into lines at the whole sequence, so `bot$` matches `bot\r\n`. The convention
+    /// is set with a leading pcre2 verb such as `(*CRLF)`, which shows in
+    /// [`BotDetector::merged_pattern`]. Defaults to pcre2's default, usually [`Newline::Lf`].
+    #[must_use]
+    pub fn newline(mut self, newline: Newline) -> Self {
+        self.options.newline = Some(newline);
+        self
+    }
+
+    /// Replace the browser engine tokens used by [`BotDetector::looks_automated`].
+    ///
+    /// Tokens are matched as case-insensitive substrings. Defaults to
+    /// [`DEFAULT_ENGINE_TOKENS`].
+    #[must_use]
+    pub fn engine_tokens(mut self, tokens: &[String]) -> Self {
+        self.options.engine_tokens = tokens.to_vec();
+        self
+    }
+
+    /// Set exclusion patterns, used by [`BotDetector::classify_filtered`].
+    ///
+    /// Exclusions are compiled with the same options as the bot patterns. A user agent matching
+    /// any of them is never reported by `classify_filtered`, whatever bot patterns it matches.
+    #[must_use]
+    pub fn exclusions(mut self, exclusions: &[String]) -> Self {
+        self.options.exclusions = exclusions.to_vec();
+        self
+    }
+
+    /// Set the patterns used by [`BotDetector::check_client_hints`] on the `Sec-CH-UA` header.
+    ///
+    /// They form a separate set, compiled with the same options as the bot patterns but never
+    /// applied to user agents, just as the bot patterns are never applied to client hints.
+    #[must_use]
+    pub fn client_hint_patterns(mut self, patterns: &[String]) -> Self {
+        self.options.client_hints = patterns.to_vec();
+        self
+    }
+
+    /// Count the checks made with the detector, for [`BotDetector::metrics_text`].
+    ///
+    /// Every call of [`BotDetector::is_bot`], [`BotDetector::classify`] or
+    /// [`BotDetector::explain`], of their `try_` variants, or of a method built on them such as
+    /// [`BotDetector::scan_reader`], counts one check, and one bot per category when it finds
+    /// one. The counters are atomic, so a detector shared between threads counts the checks of
+    /// all of them. Counting costs an atomic increment per check, and `is_bot` runs the
+    /// per-pattern regexes on bots to find their category. The user agents passed to
+    /// [`BotDetectorBuilder::assert_detects`] are not counted.
+    ///
+    /// Only available with the `metrics` feature. Defaults to `false`.
+    #[cfg(feature = "metrics")]
+    #[must_use]
+    pub fn count_hits(mut self, yes: bool) -> Self {
+        self.options.count_hits = yes;
+        self
+    }
+
+    /// Refuse to build a detector from more than `limit` patterns.
+    ///
+    /// The count is checked once the patterns are parsed and before anything is compiled, and
+    /// again by [`BotDetector::add_patterns`]. This guards against a corrupted or oversized
+    /// patterns file fetched from an untrusted source. Defaults to no limit.
+    #[must_use]
+    pub fn max_patterns(mut self, limit: usize) -> Self {
+        self.options.max_patterns = Some(limit);
+        self
+    }
+
+    /// Require the compiled detector to detect each of the given user agents as a bot.
+    ///
+    /// Once the patterns are compiled, [`BotDetectorBuilder::build`] checks every user agent
+    /// with [`BotDetector::is_bot`] and fails with `BotDetectorError::AssertionFailed` on the
+    /// first one that doesn't match. This catches pattern regressions at load time, e.g. in a
+    /// deploy pipeline. Calling it again adds to the list. Defaults to no user agents.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// # use botagent::errors::BotDetectorError;
+    /// let result = BotDetector::builder()
+    ///     .patterns(&["Googlebot".to_string()])
+    ///     .assert_detects(&["Googlebot/2.1", "bingbot/2.0"])
+    ///     .build();
+    /// assert!(matches!(result, Err(BotDetectorError::AssertionFailed { ua }) if ua == "bingbot/2.0"));
+    /// ```
+    #[must_use]
+    pub fn assert_detects(mut self, user_agents: &[&str]) -> Self {
+        self.assert_detects
+            .extend(user_agents.iter().map(ToString::to_string));
+        self
+    }
+
+    /// Set how [`BotDetector::is_bot`], [`BotDetector::classify`] and their `try_` variants treat
+    /// a pcre2 error raised while matching, once any retry without JIT has failed too.
+    ///
+    /// With [`ErrorPolicy::AsBot`], `is_bot` returns `true` and `classify` returns a `BotInfo`
+    /// with an empty pattern and matched text, since no pattern can be credited. Only
+    /// [`ErrorPolicy::Propagate`] lets the `try_` methods return the error; with the other
+    /// policies they answer like the infallible methods. The other query methods treat an error
+    /// as no match. Defaults to [`ErrorPolicy::AsNotBot`].
+    #[must_use]
+    pub fn on_match_error(mut self, policy: ErrorPolicy) -> Self {
+        self.options.on_match_error = policy;
+        self
+    }
+
+    /// Treat an empty user agent as a bot in [`BotDetector::is_bot`], for endpoints where a
+    /// missing user agent is itself suspicious.
+    ///
+    /// This also applies to the checks answering like `is_bot`, such as
+    /// [`BotDetector::try_is_bot`], [`BotDetector::is_bot_bytes`] and [`PreparedUa::is_bot`].
+    /// The methods reporting a match, e.g. [`BotDetector::classify`], still return nothing for
+    /// an empty user agent, since no pattern matched it. A user agent made only of whitespace
+    /// is not empty. Defaults to `false`.
+    #[must_use]
+    pub fn empty_ua_is_bot(mut self, yes: bool) -> Self {
+        self.options.empty_ua_is_bot = yes;
+        self
+    }
+
+    /// Make [`BotDetector::is_bot_strict`] reject user agents that are not valid UTF-8 instead
+    /// of converting them lossily, so data-quality issues surface as errors. Defaults to
+    /// `false`.
+    #[must_use]
+    pub fn require_utf8(mut self, yes: bool) -> Self {
+        self.options.require_utf8 = yes;
+        self
+    }
+
+    /// Cap the length in bytes of the matched text reported by [`BotDetector::is_bot_match`],
+    /// [`BotDetector::classify`] and the other methods returning it.
+    ///
+    /// A greedy pattern such as `.*` can match most of a crafted, very long user agent; a longer
+    /// matched text is cut at the last character boundary within `limit` and ends with
+    /// [`TRUNCATION_MARKER`]. Spans are not affected. Defaults to
+    /// `Some(`[`DEFAULT_MAX_MATCH_LEN`]`)`; pass `None` to disable the cap.
+    #[must_use]
+    pub fn max_match_len(mut self, limit: Option<usize>) -> Self {
+        self.options.max_match_len = limit;
+        self
+    }
+
+    /// Replace the headless browser signatures used by [`BotDetector::is_headless`].
+    ///
+    /// Like the client hint patterns, they form a separate set compiled with the same options
+    /// as the bot patterns. An empty list disables headless detection. Defaults to the bundled
+    /// signatures.
+    #[must_use]
+    pub fn headless_patterns(mut self, patterns: &[String]) -> Self {
+        self.options.headless = Some(patterns.to_vec());
+        self
+    }
+
+    /// Replace the names of the good bots recognized by [`BotDetector::is_good_bot`].
+    ///
+    /// Names are compared case-insensitively. An empty list makes every bot a bad one.
+    /// Defaults to the bundled names.
+    #[must_use]
+    pub fn good_bots(mut self, names: &[String]) -> Self {
+        self.options.good_bots = Some(names.to_vec());
+        self
+    }
+
+    /// Read the patterns and compile the detector.
+    ///
+    /// Problems found once the patterns are read are collected rather than reported one at a
+    /// time: exceeding [`BotDetectorBuilder::max_patterns`], every pattern that cannot be
+    /// compiled, invalid exclusion, client hint or headless patterns, and every user agent
+    /// passed to [`BotDetectorBuilder::assert_detects`] that is not a bot. The assertions only
+    /// run once all the patterns compile. Reading or parsing the patterns, and finding none,
+    /// fail early, since there is nothing to check afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BotDetectorError::Empty` if no patterns were given, or a `BotDetectorError` if
+    /// the patterns cannot be read or parsed. Otherwise, returns the only problem found on its
+    /// own, e.g. `BotDetectorError::TooManyPatterns`, `BotDetectorError::AssertionFailed` or
+    /// `BotDetectorError::RegexCompile`, or `BotDetectorError::Multiple` with all of them, in
+    /// the order listed above, if there are several.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// # use botagent::errors::BotDetectorError;
+    /// let result = BotDetector::builder()
+    ///     .patterns(&["Googlebot".to_string(), "curl".to_string()])
+    ///     .max_patterns(1)
+    ///     .assert_detects(&["Wget/1.21"])
+    ///     .build();
+    /// assert!(matches!(result, Err(BotDetectorError::Multiple(errors)) if errors.len() == 2));
+    /// ```
+    pub fn build(self) -> Result<BotDetector, BotDetectorError> {
+        let entries = match self.source {
+            Source::None => Vec::new(),
+            Source::Entries(entries) => entries,
+            Source::JsonPath(json_path) => pattern::read_entries(&json_path)?,
+            Source::Dir(dir) => pattern::read_entries_from_dir(&dir, self.recursive)?,
+        };
+
+        let mut errors = Vec::new();
+        let detector = BotDetector::compile(entries, self.options, &mut errors);
+
+        if let Some(detector) = &detector {
+            errors.extend(
+                self.assert_detects
+                    .into_iter()
+                    .filter(|ua| !detector.is_bot(ua))
+                    .map(|ua| BotDetectorError::AssertionFailed { ua }),
+            );
+        }
+
+        BotDetectorError::from_all(errors)?;
+        #[cfg_attr(not(feature = "metrics"), allow(unused_mut))]
+        let Some(mut detector) = detector
+        else {
+            unreachable!("an error is recorded whenever compilation fails");
+        };
+
+        #[cfg(feature = "metrics")]
+        if detector.options.count_hits {
+            let categories = detector
+                .entries
+                .iter()
+                .map(|entry| entry.category.as_deref().unwrap_or(UNCATEGORIZED));
+            detector.counters = Some(Arc::new(Counters::new(categories)));
+        }
+
+        Ok(detector)
+    }
+}
+
+impl BotDetector {
+    /// Build a detector from a list of patterns.
+    ///
+    /// # Arguments
+    ///
+    /// * `patterns` - The bot patterns, merged with `|` into a single caseless regex.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BotDetectorError::Empty` if `patterns` is empty, or a `BotDetectorError` if the
+    /// merged pattern cannot be compiled.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["Googlebot".to_string()]).unwrap();
+    /// assert!(detector.is_bot("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)"));
+    /// ```
+    pub fn from_patterns(patterns: &[String]) -> Result<Self, BotDetectorError> {
+        Self::builder().patterns(patterns).build()
+    }
+
+    /// Build a detector from a list of pattern entries, keeping their metadata.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - The pattern entries, merged with `|` into a single caseless regex.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BotDetectorError::Empty` if `entries` is empty, or a `BotDetectorError` if the
+    /// merged pattern cannot be compiled.
+    pub fn from_entries(entries: &[PatternEntry]) -> Result<Self, BotDetectorError> {
+        Self::builder().entries(entries).build()
+    }
+
+    /// Create a builder to configure how the detector is compiled.
+    #[must_use]
+    pub fn builder() -> BotDetectorBuilder {
+        BotDetectorBuilder::new()
+    }
+
+    fn with_options(
+        entries: Vec<PatternEntry>,
+        options: Options,
+    ) -> Result<Self, BotDetectorError> {
+        let mut errors = Vec::new();
+        let detector = Self::compile(entries, options, &mut errors);
+        BotDetectorError::from_all(errors)?;
+
+        Ok(detector.expect("an error is recorded whenever compilation fails"))
+    }
+
+    /// Compile the detector, recording every problem found in `errors` rather than stopping at
+    /// the first one.
+    ///
+    /// Returns `None` if there are no patterns or if any of them cannot be compiled. The
+    /// detector is still returned when only the pattern count exceeds `max_patterns`, so the
+    /// builder's assertions can run on it.
+    fn compile(
+        mut entries: Vec<PatternEntry>,
+        options: Options,
+        errors: &mut Vec<BotDetectorError>,
+    ) -> Option<Self> {
+        // a blank branch in the merged regex would match every user agent
+        let dropped = entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| is_blank(&entry.pattern))
+            .map(|(index, _)| index)
+            .collect();
+        entries.retain(|entry| !is_blank(&entry.pattern));
+
+        if entries.is_empty() {
+            errors.push(BotDetectorError::Empty);
+            return None;
+        }
+        if let Err(e) = options.check_count(entries.len()) {
+            errors.push(e);
+        }
+
+        let compiled: Option<Vec<_>> = options
+            .compile_each_results(&entries)
+            .into_iter()
+            .map(|result| record(result, errors))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .collect();
+        // the merged regex is only worth compiling once every pattern compiles on its own
+        let regex = match compiled {
+            Some(_) => record(options.compile_merged(&entries), errors),
+            None => None,
+        };
+        let exclusion = record(options.compile_exclusion(), errors);
+        let client_hints = record(options.compile_client_hints(), errors);
+        let headless = record(options.compile_headless(), errors);
+        let prefilter = options.prefilter(&entries);
+
+        Some(Self {
+            entries,
+            regex: Arc::new(regex?),
+            compiled: Arc::new(compiled?),
+            options,
+            injected: false,
+            exclusion: exclusion?.map(Arc::new),
+            prefilter,
+            client_hints: client_hints?,
+            headless: headless?,
+            dropped,
+            marked: Arc::default(),
+            interpreted: Arc::default(),
+            pattern_version: None,
+            #[cfg(feature = "metrics")]
+            counters: None,
+        })
+    }
+
+    /// Build a detector from a merged pattern generated at build time, see
+    /// [`crate::codegen::emit_merged_pattern`].
+    ///
+    /// The pattern is compiled caseless like the patterns of a default detector, and is
+    /// treated as the detector's single pattern as with [`BotDetector::from_regex`], so
+    /// classifications report the whole merged pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The merged bot pattern.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BotDetectorError` if the pattern cannot be compiled.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// const MERGED_PATTERN: &str = "Googlebot|curl";
+    /// let detector = BotDetector::from_static_pattern(MERGED_PATTERN).unwrap();
+    /// assert!(detector.is_bot("googlebot/2.1"));
+    /// ```
+    pub fn from_static_pattern(pattern: &'static str) -> Result<Self, BotDetectorError> {
+        Ok(Self::from_regex(
+            Options::default().regex_builder().build(pattern)?,
+        ))
+    }
+
+    /// Build a detector around an already compiled regex.
+    ///
+    /// The regex is used as is, with whatever options it was compiled with, and is treated as
+    /// the detector's single pattern. Adding patterns later recompiles its source with the
+    /// default options.
+    ///
+    /// # Arguments
+    ///
+    /// * `regex` - The compiled bot pattern.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// # use pcre2::bytes::Regex;
+    /// let detector = BotDetector::from_regex(Regex::new("Googlebot").unwrap());
+    /// assert!(detector.is_bot("Googlebot/2.1"));
+    /// assert!(!detector.is_bot("googlebot/2.1"));
+    /// ```
+    #[must_use]
+    pub fn from_regex(regex: Regex) -> Self {
+        Self {
+            entries: vec![PatternEntry::from(regex.as_str().to_string())],
+            compiled: Arc::new(vec![(regex.as_str().to_string(), regex.clone())]),
+            regex: Arc::new(regex),
+            options: Options::default(),
+            injected: true,
+            exclusion: None,
+            prefilter: None,
+            client_hints: None,
+            headless: Some(Arc::clone(&BUNDLED_HEADLESS)),
+            dropped: Vec::new(),
+            marked: Arc::default(),
+            interpreted: Arc::default(),
+            pattern_version: None,
+            #[cfg(feature = "metrics")]
+            counters: None,
+        }
+    }
+
+    /// Build a detector from a JSON file containing patterns.
+    ///
+    /// # Arguments
+    ///
+    /// * `json_path` - Path to the JSON file containing bot patterns.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BotDetectorError` if the file cannot be read or parsed, or if the patterns
+    /// cannot be compiled.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_json_path("patterns.json").unwrap();
+    /// ```
+    pub fn from_json_path(json_path: &str) -> Result<Self, BotDetectorError> {
+        Self::builder().json_path(json_path).build()
+    }
+
+    /// Build a detector from the content of a patterns file held in memory, e.g. embedded with
+    /// `include_bytes!` or memory-mapped.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The JSON content, in the patterns file format.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BotDetectorError` if the content is not valid UTF-8 JSON, or if the patterns
+    /// cannot be compiled.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_json_bytes(br#"["Googlebot", "curl"]"#).unwrap();
+    /// assert!(detector.is_bot("curl/7.68.0"));
+    /// ```
+    pub fn from_json_bytes(bytes: &[u8]) -> Result<Self, BotDetectorError> {
+        let entries = pattern::parse_entries_slice(bytes)?;
+
+        Self::from_entries(&entries)
+    }
+
+    /// Build a detector from the pattern list bundled with the crate, so no patterns file has to
+    /// be deployed.
+    ///
+    /// The list is embedded at compile time, and its version, [`crate::BUNDLED_PATTERNS_VERSION`],
+    /// is recorded in the detector's [`BotDetector::pattern_version`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BotDetectorError` if the bundled patterns cannot be compiled, which only
+    /// happens if the crate ships a broken list.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::{BotDetector, BUNDLED_PATTERNS_VERSION};
+    /// let detector = BotDetector::with_default_patterns().unwrap();
+    /// assert!(detector.is_bot("Googlebot/2.1"));
+    /// assert_eq!(detector.pattern_version(), Some(BUNDLED_PATTERNS_VERSION));
+    /// ```
+    pub fn with_default_patterns() -> Result<Self, BotDetectorError> {
+        let mut detector = Self::from_json_bytes(DEFAULT_PATTERNS_JSON.as_bytes())?;
+        detector.pattern_version = Some(crate::BUNDLED_PATTERNS_VERSION);
+
+        Ok(detector)
+    }
+
+    /// Returns the version of the bundled pattern list the detector was built from.
+    ///
+    /// `None` unless the detector comes from [`BotDetector::with_default_patterns`], or once
+    /// [`BotDetector::add_patterns`] changed its pattern set.
+    #[must_use]
+    pub fn pattern_version(&self) -> Option<&str> {
+        self.pattern_version
+    }
+
+    /// Build a detector from the patterns of several JSON files.
+    ///
+    /// The files are concatenated in the given order and patterns appearing more than once are
+    /// only kept the first time.
+    ///
+    /// # Arguments
+    ///
+    /// * `json_paths` - Paths to the JSON files containing bot patterns.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BotDetectorError::InFile`, naming the offending path, if a file cannot be read
+    /// or parsed, or a `BotDetectorError` if the patterns cannot be compiled.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use botagent::BotDetector;
+    /// let detector =
+    ///     BotDetector::from_json_paths(&["crawlers.json", "scrapers.json", "internal.json"]).unwrap();
+    /// ```
+    pub fn from_json_paths(json_paths: &[&str]) -> Result<Self, BotDetectorError> {
+        let entries = pattern::read_entries_from(json_paths)?;
+
+        Self::from_entries(&entries)
+    }
+
+    /// Build a detector from the patterns of every `.json` file in a directory, e.g. one file
+    /// per vendor.
+    ///
+    /// The files are read in path order and merged as with [`BotDetector::from_json_paths`].
+    /// Subdirectories are skipped; use [`BotDetectorBuilder::recursive`] to include them.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - Path to the directory containing the JSON files.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BotDetectorError::InFile`, naming the offending path, if the directory cannot be
+    /// listed or a file cannot be read or parsed, or a `BotDetectorError` if the patterns cannot
+    /// be compiled.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_dir("signatures").unwrap();
+    /// let detector = BotDetector::builder().dir("signatures").recursive(true).build().unwrap();
+    /// ```
+    pub fn from_dir(dir: &str) -> Result<Self, BotDetectorError> {
+        Self::builder().dir(dir).build()
+    }
+
+    /// Build a detector from a JSON file that may contain `//` and `/* */` comments (JSONC).
+    ///
+    /// # Arguments
+    ///
+    /// * `json_path` - Path to the JSONC file containing bot patterns.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BotDetectorError` if the file cannot be read or parsed, or if the patterns
+    /// cannot be compiled.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_jsonc_path("patterns.jsonc").unwrap();
+    /// ```
+    pub fn from_jsonc_path(json_path: &str) -> Result<Self, BotDetectorError> {
+        let entries = pattern::read_entries_jsonc(json_path)?;
+
+        Self::from_entries(&entries)
+    }
+
+    /// Build a detector from a plain text file holding one pattern per line, for signature
+    /// files that were never converted to JSON.
+    ///
+    /// Blank lines and lines starting with `#` are ignored, see
+    /// [`pattern::read_patterns_lines`].
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the text file containing bot patterns.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BotDetectorError` if the file cannot be read, or if the patterns cannot be
+    /// compiled.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_lines_path("patterns.txt").unwrap();
+    /// ```
+    pub fn from_lines_path(path: &str) -> Result<Self, BotDetectorError> {
+        let patterns = pattern::read_patterns_lines(path)?;
+
+        Self::from_patterns(&patterns)
+    }
+
+    /// Check if the given user agent matches any of the detector's patterns.
+    ///
+    /// An empty user agent is not considered a bot, unless the detector is built with
+    /// [`BotDetectorBuilder::empty_ua_is_bot`].
+    ///
+    /// Before running the regex engine, the user agent goes through a prefilter built from the
+    /// bytes each pattern can start its match with: a user agent containing none of them, or
+    /// not starting with one for `^`-anchored patterns, is rejected right away. The results are
+    /// identical to the regex alone. When a pattern uses a construct the prefilter doesn't
+    /// understand, or can match the empty string, the prefilter is disabled. It pays off for
+    /// pattern sets starting with distinctive bytes; the bundled list, which has patterns
+    /// starting with broad classes, gets little out of it.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    #[must_use]
+    pub fn is_bot(&self, user_agent: &str) -> bool {
+        if user_agent.is_empty() {
+            return self.options.empty_ua_is_bot;
+        }
+
+        self.is_bot_subject(self.options.subject(user_agent).as_bytes())
+    }
+
+    /// Check if the given user agent matches the detector's patterns, reporting match errors.
+    ///
+    /// With [`ErrorPolicy::Propagate`] set by [`BotDetectorBuilder::on_match_error`], this is
+    /// [`BotDetector::is_bot`] without the retry of [`BotDetectorBuilder::jit`]: when the JIT
+    /// stack runs out, the error is returned so the caller can decide what to do with the
+    /// input. Other pcre2 match errors, e.g. hitting the match or depth limit, are returned as
+    /// well. With the other policies, this returns `is_bot`'s answer.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BotDetectorError::JitStack` if matching runs out of JIT stack, or
+    /// `BotDetectorError::Match` with the pcre2 error for any other match error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::{BotDetector, ErrorPolicy};
+    /// let detector = BotDetector::builder()
+    ///     .patterns(&["Googlebot".to_string()])
+    ///     .on_match_error(ErrorPolicy::Propagate)
+    ///     .build()
+    ///     .unwrap();
+    /// assert!(detector.try_is_bot("Googlebot/2.1").unwrap());
+    /// ```
+    pub fn try_is_bot(&self, user_agent: &str) -> Result<bool, BotDetectorError> {
+        if self.options.on_match_error != ErrorPolicy::Propagate {
+            return Ok(self.is_bot(user_agent));
+        }
+
+        if user_agent.is_empty() {
+            return Ok(self.options.empty_ua_is_bot);
+        }
+
+        let subject = self.options.subject(user_agent);
+
+        let is_bot = match &self.prefilter {
+            Some(prefilter) if !prefilter.may_match(subject.as_bytes()) => false,
+            _ => self
+                .regex
+                .is_match(subject.as_bytes())
+                .map_err(match_error)?,
+        };
+
+        #[cfg(feature = "metrics")]
+        if let Some(counters) = &self.counters {
+            counters.record(is_bot.then(|| self.category_of(subject.as_bytes())));
+        }
+
+        Ok(is_bot)
+    }
+
+    /// Check if the given user agent bytes match the detector's patterns.
+    ///
+    /// By default, invalid UTF-8 sequences are replaced with `U+FFFD` before matching, as with
+    /// [`String::from_utf8_lossy`]. With [`BotDetectorBuilder::require_utf8`], they are
+    /// reported as an error instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent bytes to be checked.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BotDetectorError::InvalidUtf8` if `require_utf8` is set and the bytes are not
+    /// valid UTF-8.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::builder()
+    ///     .patterns(&["Googlebot".to_string()])
+    ///     .require_utf8(true)
+    ///     .build()
+    ///     .unwrap();
+    /// assert!(detector.is_bot_strict(b"Googlebot/2.1").unwrap());
+    /// assert!(detector.is_bot_strict(b"Googlebot/\xff").is_err());
+    /// ```
+    pub fn is_bot_strict(&self, user_agent: &[u8]) -> Result<bool, BotDetectorError> {
+        if self.options.require_utf8 {
+            let user_agent =
+                std::str::from_utf8(user_agent).map_err(BotDetectorError::InvalidUtf8)?;
+            return Ok(self.is_bot(user_agent));
+        }
+
+        Ok(self.is_bot_bytes(user_agent))
+    }
+
+    /// Check if the given user agent bytes match the detector's patterns.
+    ///
+    /// Invalid UTF-8 sequences are replaced with `U+FFFD` before matching, as with
+    /// [`String::from_utf8_lossy`]; NUL and other control bytes are kept and matched like any
+    /// other character, e.g. by `\x00` in a pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent bytes to be checked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["Googlebot".to_string()]).unwrap();
+    /// assert!(detector.is_bot_bytes(b"\0Googlebot/2.1\xff"));
+    /// ```
+    #[must_use]
+    pub fn is_bot_bytes(&self, user_agent: &[u8]) -> bool {
+        self.is_bot(&String::from_utf8_lossy(user_agent))
+    }
+
+    /// Check if any token of the user agent, on its own, matches the detector's patterns.
+    ///
+    /// The user agent is split on whitespace and `;`, and each non-empty token is matched
+    /// separately, so a pattern can't match across two tokens, e.g. `bot c` doesn't match
+    /// `Googlebot compatible`, and `^` and `$` anchor at the ends of every token. Meant for user
+    /// agents carrying `key=value` parameters or other delimiter-structured parts. The options
+    /// rewriting the user agent, such as [`BotDetectorBuilder::strip_quotes`], apply to the
+    /// whole user agent before it is split.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["^bot=1$".to_string()]).unwrap();
+    /// assert!(detector.matches_any_token("Mozilla/5.0 app=feed;bot=1"));
+    /// assert!(!detector.matches_any_token("Mozilla/5.0 app=feed;bot=12"));
+    /// ```
+    #[must_use]
+    pub fn matches_any_token(&self, user_agent: &str) -> bool {
+        let subject = self.options.subject(user_agent);
+
+        subject
+            .split(|c: char| c.is_whitespace() || c == ';')
+            .filter(|token| !token.is_empty())
+            .any(|token| match &self.prefilter {
+                Some(prefilter) if !prefilter.may_match(token.as_bytes()) => false,
+                _ => match self.find_merged(token.as_bytes()) {
+                    Ok(matched) => matched.is_some(),
+                    Err(_) => self.options.on_match_error == ErrorPolicy::AsBot,
+                },
+            })
+    }
+
+    /// Same as [`BotDetector::is_bot`], for a user agent already prepared for matching.
+    fn is_bot_subject(&self, subject: &[u8]) -> bool {
+        let is_bot = match &self.prefilter {
+            Some(prefilter) if !prefilter.may_match(subject) => false,
+            _ => match self.find_merged(subject) {
+                Ok(matched) => matched.is_some(),
+                Err(_) => self.options.on_match_error == ErrorPolicy::AsBot,
+            },
+        };
+
+        #[cfg(feature = "metrics")]
+        if let Some(counters) = &self.counters {
+            counters.record(is_bot.then(|| self.category_of(subject)));
+        }
+
+        is_bot
+    }
+
+    /// Returns the category of the entry the bot subject is attributed to.
+    #[cfg(feature = "metrics")]
+    fn category_of(&self, subject: &[u8]) -> &str {
+        self.find_merged(subject)
+            .ok()
+            .flatten()
+            .and_then(|matched| self.pattern_index_at(subject, matched.start()))
+            .and_then(|index| self.entries[index].category.as_deref())
+            .unwrap_or(UNCATEGORIZED)
+    }
+
+    /// Returns the counters of a detector built with [`BotDetectorBuilder::count_hits`].
+    #[cfg(feature = "metrics")]
+    pub(crate) fn counters(&self) -> Option<&Counters> {
+        self.counters.as_deref()
+    }
+
+    /// Find the leftmost match of the merged regex, retrying without JIT if the JIT stack runs
+    /// out.
+    fn find_merged<'s>(&self, subject: &'s [u8]) -> Result<Option<Match<'s>>, Pcre2Error> {
+        match self.regex.find(subject) {
+            Err(e) if is_jit_stack_error(&e) => match self.interpreted_regex() {
+                Some(interpreted) => interpreted.find(subject),
+                None => Err(e),
+            },
+            result => result,
+        }
+    }
+
+    /// Returns the merged regex compiled without JIT, compiling it on first use. `None` for an
+    /// injected regex, whose flags are unknown.
+    fn interpreted_regex(&self) -> Option<&Regex> {
+        self.interpreted
+            .get_or_init(|| {
+                if self.injected {
+                    return None;
+                }
+                let mut builder = self.options.regex_builder();
+                builder.jit_if_available(false);
+                builder.build(self.regex.as_str()).ok()
+            })
+            .as_ref()
+    }
+
+    /// Prepare the user agent for several queries, normalizing it once.
+    ///
+    /// The options that rewrite the user agent before matching, such as
+    /// [`BotDetectorBuilder::strip_quotes`], [`BotDetectorBuilder::ignore_url_segments`] or
+    /// [`BotDetectorBuilder::precompute_lowercase`], otherwise run on every query. The
+    /// returned [`PreparedUa`] borrows both the detector and the user agent, so it can't outlive
+    /// either; it is meant to live for the duration of a request.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::builder()
+    ///     .patterns(&["Googlebot".to_string(), "compatible".to_string()])
+    ///     .precompute_lowercase(true)
+    ///     .build()
+    ///     .unwrap();
+    /// let prepared = detector.prepare("Mozilla/5.0 (compatible; Googlebot/2.1)");
+    /// assert!(prepared.is_bot());
+    /// assert_eq!(prepared.classify().unwrap().pattern, "compatible");
+    /// assert_eq!(prepared.match_count(), 2);
+    /// ```
+    #[must_use]
+    pub fn prepare<'d, 'u>(&'d self, user_agent: &'u str) -> PreparedUa<'d, 'u> {
+        PreparedUa {
+            detector: self,
+            empty: user_agent.is_empty(),
+            subject: self.options.subject(user_agent),
+        }
+    }
+
+    /// Check if the given user agent, stripped of a single pair of surrounding quotes, matches
+    /// the detector's patterns.
+    ///
+    /// This is [`BotDetector::is_bot`] with quote stripping applied to a single call, whether or
+    /// not the detector was built with [`BotDetectorBuilder::strip_quotes`]. Only matching
+    /// outermost quotes are stripped, internal ones are left alone.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["^Googlebot".to_string()]).unwrap();
+    /// assert!(!detector.is_bot("\"Googlebot/2.1\""));
+    /// assert!(detector.is_bot_trimmed("\"Googlebot/2.1\""));
+    /// ```
+    #[must_use]
+    pub fn is_bot_trimmed(&self, user_agent: &str) -> bool {
+        self.is_bot(strip_quotes(user_agent))
+    }
+
+    /// Find the substring of the user agent matched by the detector's patterns.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(String)` with the matched text or `None` if no match is found.
+    #[must_use]
+    pub fn is_bot_match(&self, user_agent: &str) -> Option<String> {
+        let subject = self.options.subject(user_agent);
+        let matched = self.find_merged(subject.as_bytes()).ok()??;
+
+        Some(self.options.matched_text(matched.as_bytes()))
+    }
+
+    /// Returns the name of the bot the user agent was classified as, the display-friendly
+    /// counterpart to [`BotDetector::is_bot_match`].
+    ///
+    /// This is the `name` of the entry found by [`BotDetector::classify`], falling back to the
+    /// matched text when the entry has no name.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if the user agent is not a bot.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// # use botagent::pattern::PatternEntry;
+    /// let detector = BotDetector::from_entries(&[
+    ///     PatternEntry { pattern: "googlebot".to_string(), name: Some("Googlebot".to_string()), ..PatternEntry::default() },
+    ///     PatternEntry::from("curl".to_string()),
+    /// ])
+    /// .unwrap();
+    /// assert_eq!(detector.name_of_match("Googlebot/2.1"), Some("Googlebot".to_string()));
+    /// assert_eq!(detector.name_of_match("CURL/7.68.0"), Some("CURL".to_string()));
+    /// ```
+    #[must_use]
+    pub fn name_of_match(&self, user_agent: &str) -> Option<String> {
+        let info = self.classify(user_agent)?;
+
+        Some(info.name.unwrap_or(info.matched_text))
+    }
+
+    /// Returns the stable id of the entry the user agent was classified by, see
+    /// [`BotDetector::classify`] and [`PatternEntry::stable_id`].
+    ///
+    /// The id comes from the entry's `id` field, or else is a hash of its pattern text, so
+    /// detections can be correlated across pattern file versions.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if the user agent is not a bot.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// # use botagent::pattern::PatternEntry;
+    /// let detector = BotDetector::from_entries(&[PatternEntry {
+    ///     pattern: "googlebot".to_string(),
+    ///     id: Some("google-1".to_string()),
+    ///     ..PatternEntry::default()
+    /// }])
+    /// .unwrap();
+    /// assert_eq!(detector.detect_id("Googlebot/2.1"), Some("google-1".to_string()));
+    /// ```
+    #[must_use]
+    pub fn detect_id(&self, user_agent: &str) -> Option<String> {
+        if user_agent.is_empty() {
+            return None;
+        }
+
+        let subject = self.options.subject(user_agent);
+        let matched = self.find_merged(subject.as_bytes()).ok()??;
+        let index = self.pattern_index_at(subject.as_bytes(), matched.start())?;
+
+        Some(self.entries[index].stable_id())
+    }
+
+    /// Returns the category of the entry the user agent was classified by, see
+    /// [`BotDetector::classify`].
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if the user agent is not a bot or the matching entry has no category.
+    #[must_use]
+    pub fn detect_category(&self, user_agent: &str) -> Option<String> {
+        self.classify(user_agent)?.category
+    }
+
+    /// Count the bots of each category in a batch of user agents.
+    ///
+    /// Each user agent is classified with [`BotDetector::classify`]; bots whose entry has no
+    /// category are counted under [`UNCATEGORIZED`], and user agents that are not bots are not
+    /// counted at all.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agents` - The user agents to be checked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::{BotDetector, UNCATEGORIZED};
+    /// # use botagent::pattern::PatternEntry;
+    /// let detector = BotDetector::from_entries(&[
+    ///     PatternEntry { pattern: "Googlebot".to_string(), category: Some("crawler".to_string()), ..PatternEntry::default() },
+    ///     PatternEntry::from("curl".to_string()),
+    /// ])
+    /// .unwrap();
+    /// let histogram = detector.category_histogram(&["Googlebot/2.1", "curl/7.68.0", "Mozilla/5.0"]);
+    /// assert_eq!(histogram["crawler"], 1);
+    /// assert_eq!(histogram[UNCATEGORIZED], 1);
+    /// ```
+    #[must_use]
+    pub fn category_histogram(&self, user_agents: &[&str]) -> HashMap<String, u64> {
+        let mut histogram = HashMap::new();
+
+        for info in user_agents.iter().filter_map(|ua| self.classify(ua)) {
+            let category = info.category.unwrap_or_else(|| UNCATEGORIZED.to_string());
+            *histogram.entry(category).or_insert(0) += 1;
+        }
+
+        histogram
+    }
+
+    /// Count the patterns of each category matching the user agent on their own, e.g. to apply
+    /// category-specific thresholds.
+    ///
+    /// Patterns without a category are counted under [`UNCATEGORIZED`], and categories with no
+    /// matching pattern are left out.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// # use botagent::pattern::PatternEntry;
+    /// let scraper = |pattern: &str| PatternEntry {
+    ///     pattern: pattern.to_string(),
+    ///     category: Some("scraper".to_string()),
+    ///     ..PatternEntry::default()
+    /// };
+    /// let detector = BotDetector::from_entries(&[scraper("scrapy"), scraper("python")]).unwrap();
+    /// assert_eq!(detector.category_match_counts("Scrapy/2.11 (python)")["scraper"], 2);
+    /// ```
+    #[must_use]
+    pub fn category_match_counts(&self, user_agent: &str) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+
+        if user_agent.is_empty() {
+            return counts;
+        }
+
+        let subject = self.options.subject(user_agent);
+
+        for (entry, _) in self.pattern_matches(subject.as_bytes()) {
+            let category = entry.category.as_deref().unwrap_or(UNCATEGORIZED);
+            *counts.entry(category.to_string()).or_insert(0) += 1;
+        }
+
+        counts
+    }
+
+    /// Returns the text captured by a numbered group of the pattern that matched the user agent.
+    ///
+    /// The match is attributed to a pattern as in [`BotDetector::classify`], and `group` is
+    /// numbered within that pattern, from 1 for its first group. Group 0 is the full match, which
+    /// is what [`BotDetector::is_bot_match`] returns.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    /// * `group` - The number of the capture group.
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if the user agent is not a bot, or if the matching pattern has no such
+    /// group or the group didn't participate in the match.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["(\\w+)bot/".to_string()]).unwrap();
+    /// assert_eq!(detector.match_group("Googlebot/2.1", 0), Some("Googlebot/".to_string()));
+    /// assert_eq!(detector.match_group("Googlebot/2.1", 1), Some("Google".to_string()));
+    /// assert_eq!(detector.match_group("Googlebot/2.1", 2), None);
+    /// ```
+    #[must_use]
+    pub fn match_group(&self, user_agent: &str, group: usize) -> Option<String> {
+        if user_agent.is_empty() {
+            return None;
+        }
+
+        let subject = self.options.subject(user_agent);
+        let subject = subject.as_bytes();
+        let matched = self.find_merged(subject).ok()??;
+        let (_, regex) = &self.compiled[self.pattern_index_at(subject, matched.start())?];
+
+        let mut locations = regex.capture_locations();
+        regex
+            .captures_read_at(&mut locations, subject, matched.start())
+            .ok()??;
+        let (start, end) = locations.get(group)?;
+
+        Some(self.options.matched_text(&subject[start..end]))
+    }
+
+    /// Returns the name of the bot and its version, captured by the pattern that matched the
+    /// user agent.
+    ///
+    /// Patterns opt in by capturing the version in a group named `version`, e.g.
+    /// `Googlebot/(?<version>[\d.]+)`. Several patterns may use the name, as the merged regex
+    /// allows duplicate group names once a pattern names a group. The match is attributed to
+    /// a pattern as in [`BotDetector::classify`]; the name is the entry's `name`, or its pattern
+    /// if it has none. The version is taken from the user agent as matched, i.e. lowercased
+    /// with [`BotDetectorBuilder::precompute_lowercase`].
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if the user agent is not a bot, or if the matching pattern has no
+    /// `version` group or the group didn't participate in the match.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// # use botagent::pattern::PatternEntry;
+    /// let detector = BotDetector::from_entries(&[PatternEntry {
+    ///     pattern: "Googlebot/(?<version>[\\d.]+)".to_string(),
+    ///     name: Some("Googlebot".to_string()),
+    ///     ..PatternEntry::default()
+    /// }])
+    /// .unwrap();
+    /// assert_eq!(
+    ///     detector.extract_version("Mozilla/5.0 (compatible; Googlebot/2.1)"),
+    ///     Some(("Googlebot".to_string(), "2.1".to_string()))
+    /// );
+    /// ```
+    #[must_use]
+    pub fn extract_version(&self, user_agent: &str) -> Option<(String, String)> {
+        let (entry, version) = self.named_capture(user_agent, "version")?;
+        let name = entry.name.clone().unwrap_or_else(|| entry.pattern.clone());
+
+        Some((name, version))
+    }
+
+    /// Returns the text captured by the group named `token` in the pattern that matched the
+    /// user agent.
+    ///
+    /// Patterns expose the part of the user agent that matters, e.g. the bot's product token,
+    /// by capturing it in a group named `token`, as in `(?<token>\w+bot)/`. The match is
+    /// attributed to a pattern as in [`BotDetector::classify`], and the text is taken from the
+    /// user agent as matched, like with [`BotDetector::extract_version`].
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if the user agent is not a bot, or if the matching pattern has no `token`
+    /// group or the group didn't participate in the match.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["(?<token>\\w+bot)/".to_string()]).unwrap();
+    /// assert_eq!(
+    ///     detector.extract_token("Mozilla/5.0 (compatible; Googlebot/2.1)"),
+    ///     Some("Googlebot".to_string())
+    /// );
+    /// ```
+    #[must_use]
+    pub fn extract_token(&self, user_agent: &str) -> Option<String> {
+        self.named_capture(user_agent, "token")
+            .map(|(_, token)| token)
+    }
+
+    /// Returns the entry of the pattern that matched the user agent and the text captured by
+    /// its group named `group`.
+    fn named_capture(&self, user_agent: &str, group: &str) -> Option<(&PatternEntry, String)> {
+        if user_agent.is_empty() {
+            return None;
+        }
+
+        let subject = self.options.subject(user_agent);
+        let subject = subject.as_bytes();
+        let matched = self.find_merged(subject).ok()??;
+        let index = self.pattern_index_at(subject, matched.start())?;
+        let (_, regex) = &self.compiled[index];
+        let group = regex
+            .capture_names()
+            .iter()
+            .position(|name| name.as_deref() == Some(group))?;
+
+        let mut locations = regex.capture_locations();
+        regex
+            .captures_read_at(&mut locations, subject, matched.start())
+            .ok()??;
+        let (start, end) = locations.get(group)?;
+
+        Some((
+            &self.entries[index],
+            self.options.matched_text(&subject[start..end]),
+        ))
+    }
+
+    /// Classify the user agent, reporting which pattern matched and the text it matched.
+    ///
+    /// The merged regex decides the match; it is then attributed to the first pattern, in load
+    /// order, that matches at the same position, which is the alternative pcre2 picks.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(BotInfo)` if the user agent is a bot, otherwise `None`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::{BotDetector, BotInfo};
+    /// let detector = BotDetector::from_patterns(&["Googlebot".to_string()]).unwrap();
+    /// assert_eq!(
+    ///     detector.classify("Mozilla/5.0 (compatible; Googlebot/2.1)"),
+    ///     Some(BotInfo {
+    ///         pattern: "Googlebot".to_string(),
+    ///         name: None,
+    ///         category: None,
+    ///         matched_text: "Googlebot".to_string(),
+    ///     })
+    /// );
+    /// ```
+    #[must_use]
+    pub fn classify(&self, user_agent: &str) -> Option<BotInfo> {
+        self.explain(user_agent).map(BotInfo::from)
+    }
+
+    /// Same as [`BotDetector::classify`], also returning how long the matching took.
+    ///
+    /// Only the matching and its attribution to a pattern are timed, not the preparation of
+    /// the user agent by options such as [`BotDetectorBuilder::strip_quotes`], so user agents
+    /// that are unusually slow to match stand out. An empty user agent is not matched and
+    /// takes no time. Use `classify` when the timing isn't needed, it doesn't read the clock.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["Googlebot".to_string()]).unwrap();
+    /// let (info, elapsed) = detector.classify_timed("Googlebot/2.1");
+    /// assert_eq!(info.unwrap().pattern, "Googlebot");
+    /// println!("classified in {elapsed:?}");
+    /// ```
+    #[must_use]
+    pub fn classify_timed(&self, user_agent: &str) -> (Option<BotInfo>, Duration) {
+        if user_agent.is_empty() {
+            return (None, Duration::ZERO);
+        }
+
+        let subject = self.options.subject(user_agent);
+        let start = Instant::now();
+        let explanation = self.explain_subject(subject.as_bytes());
+        let elapsed = start.elapsed();
+
+        (explanation.map(BotInfo::from), elapsed)
+    }
+
+    /// Same as [`BotDetector::classify`], telling a user agent that matches no pattern apart
+    /// from one the regex engine failed on.
+    ///
+    /// As for [`BotDetector::try_is_bot`], errors are only returned with
+    /// [`ErrorPolicy::Propagate`], and then without a retry without JIT; the other policies
+    /// return `classify`'s answer. They are returned from both the merged regex and the
+    /// per-pattern regexes that attribute its match.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BotDetectorError::JitStack` if matching runs out of JIT stack, or
+    /// `BotDetectorError::Match` with the pcre2 error for any other match error, e.g. hitting
+    /// the match or depth limit.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["Googlebot".to_string()]).unwrap();
+    /// let info = detector.try_classify("Googlebot/2.1").unwrap().unwrap();
+    /// assert_eq!(info.pattern, "Googlebot");
+    /// assert_eq!(detector.try_classify("Mozilla/5.0").unwrap(), None);
+    /// ```
+    pub fn try_classify(&self, user_agent: &str) -> Result<Option<BotInfo>, BotDetectorError> {
+        if self.options.on_match_error != ErrorPolicy::Propagate {
+            return Ok(self.classify(user_agent));
+        }
+
+        if user_agent.is_empty() {
+            return Ok(None);
+        }
+
+        let subject = self.options.subject(user_agent);
+        let info = self.try_classify_subject(subject.as_bytes())?;
+
+        #[cfg(feature = "metrics")]
+        if let Some(counters) = &self.counters {
+            counters.record(
+                info.as_ref()
+                    .map(|info| info.category.as_deref().unwrap_or(UNCATEGORIZED)),
+            );
+        }
+
+        Ok(info)
+    }
+
+    /// Same as [`BotDetector::try_classify`] with [`ErrorPolicy::Propagate`], for a user agent
+    /// already prepared for matching.
+    fn try_classify_subject(&self, subject: &[u8]) -> Result<Option<BotInfo>, BotDetectorError> {
+        let Some(matched) = self.regex.find(subject).map_err(match_error)? else {
+            return Ok(None);
+        };
+
+        for (entry, (_, regex)) in self.entries.iter().zip(self.compiled.iter()) {
+            let at = regex
+                .find_at(subject, matched.start())
+                .map_err(match_error)?;
+            if at.is_some_and(|at| at.start() == matched.start()) {
+                return Ok(Some(bot_info(&self.options, entry, &matched)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Classify the user agent with a single regex, attributing the match through capture
+    /// groups instead of re-running the patterns on their own.
+    ///
+    /// The patterns are merged into a second regex where each of them is wrapped in a capturing
+    /// group, `(p0)|(p1)|...`, compiled on the first call. The group of pattern `i` is numbered
+    /// one past the last group of the patterns before it, so after a match the first wrapping
+    /// group that participated identifies the winning pattern. The result is the same as
+    /// [`BotDetector::classify`], which tries each pattern at the match position instead; a
+    /// detector built with [`BotDetector::from_regex`] falls back to it.
+    ///
+    /// The wrapping groups shift the numbers of the patterns' own groups, so a numbered
+    /// backreference such as `\1` no longer refers to the pattern's own group, even in the first
+    /// pattern; relative (`\g{-1}`) or named backreferences are unaffected.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(BotInfo)` if the user agent is a bot, otherwise `None`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["(curl)/(\\d+)".to_string(), "Googlebot".to_string()]).unwrap();
+    /// let info = detector.classify_merged("Googlebot/2.1").unwrap();
+    /// assert_eq!(info.pattern, "Googlebot");
+    /// ```
+    #[must_use]
+    pub fn classify_merged(&self, user_agent: &str) -> Option<BotInfo> {
+        if user_agent.is_empty() {
+            return None;
+        }
+
+        if self.injected {
+            return self.classify(user_agent);
+        }
+
+        let marked = self.marked.get_or_init(|| {
+            self.options
+                .compile_marked(&self.entries, &self.compiled)
+                .ok()
+        });
+        let Some(marked) = marked else {
+            return self.classify(user_agent);
+        };
+
+        let subject = self.options.subject(user_agent);
+        let captures = marked.regex.captures(subject.as_bytes()).ok()??;
+        let index = marked
+            .groups
+            .iter()
+            .position(|&group| captures.get(group).is_some())?;
+
+        Some(bot_info(
+            &self.options,
+            &self.entries[index],
+            &captures.get(0)?,
+        ))
+    }
+
+    /// Lazily classify many user agents against the same detector, e.g. the requests of a
+    /// server, see [`BotDetector::classify`].
+    ///
+    /// The patterns are compiled once when the detector is built, so each user agent only costs
+    /// a match; nothing is collected and user agents are classified as the iterator is advanced.
+    /// This is the intended way to use a detector, rather than the free functions taking a file
+    /// path.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agents` - The user agent strings to be checked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["Googlebot".to_string(), "curl".to_string()]).unwrap();
+    /// let requests = ["Googlebot/2.1", "Mozilla/5.0 (X11; Linux x86_64)", "curl/7.68.0"];
+    ///
+    /// for (ua, info) in requests.iter().zip(detector.classify_many(requests)) {
+    ///     match info {
+    ///         Some(info) => println!("{ua}: bot matched by {}", info.pattern),
+    ///         None => println!("{ua}: human"),
+    ///     }
+    /// }
+    ///
+    /// let bots = detector.classify_many(requests).flatten().count();
+    /// assert_eq!(bots, 2);
+    /// ```
+    pub fn classify_many<'a, I>(
+        &'a self,
+        user_agents: I,
+    ) -> impl Iterator<Item = Option<BotInfo>> + 'a
+    where
+        I: IntoIterator<Item = &'a str>,
+        I::IntoIter: 'a,
+    {
+        user_agents
+            .into_iter()
+            .map(move |user_agent| self.classify(user_agent))
+    }
+
+    /// Explain why the user agent was classified as a bot.
+    ///
+    /// This is [`BotDetector::classify`] plus the byte span of the match, packaged for display
+    /// or for an API response. When `precompute_lowercase` is enabled, the span refers to the
+    /// lowercased copy of the user agent.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(Explanation)` if the user agent is a bot, otherwise `None`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["Googlebot".to_string()]).unwrap();
+    /// let explanation = detector.explain("Mozilla/5.0 (compatible; Googlebot/2.1)").unwrap();
+    /// assert_eq!(explanation.span, (25, 34));
+    /// ```
+    #[must_use]
+    pub fn explain(&self, user_agent: &str) -> Option<Explanation> {
+        if user_agent.is_empty() {
+            return None;
+        }
+
+        self.explain_subject(self.options.subject(user_agent).as_bytes())
+    }
+
+    /// Same as [`BotDetector::explain`], for a user agent already prepared for matching.
+    fn explain_subject(&self, subject: &[u8]) -> Option<Explanation> {
+        let explanation = self.attribute(subject);
+
+        #[cfg(feature = "metrics")]
+        if let Some(counters) = &self.counters {
+            counters.record(
+                explanation
+                    .as_ref()
+                    .map(|explanation| explanation.category.as_deref().unwrap_or(UNCATEGORIZED)),
+            );
+        }
+
+        explanation
+    }
+
+    /// Attribute the subject's match to a pattern, see [`BotDetector::explain`].
+    fn attribute(&self, subject: &[u8]) -> Option<Explanation> {
+        let matched = match self.find_merged(subject) {
+            Ok(matched) => matched?,
+            Err(_) if self.options.on_match_error == ErrorPolicy::AsBot => {
+                return Some(Explanation {
+                    pattern: String::new(),
+                    name: None,
+                    category: None,
+                    matched_text: String::new(),
+                    span: (0, 0),
+                });
+            }
+            Err(_) => return None,
+        };
+        let index = self.pattern_index_at(subject, matched.start())?;
+        let entry = &self.entries[index];
+
+        Some(Explanation {
+            pattern: entry.pattern.clone(),
+            name: entry.name.clone(),
+            category: entry.category.clone(),
+            matched_text: self.options.matched_text(matched.as_bytes()),
+            span: (matched.start(), matched.end()),
+        })
+    }
+
+    /// Returns the index of the first pattern matching the subject at `start`.
+    fn pattern_index_at(&self, subject: &[u8], start: usize) -> Option<usize> {
+        (0..self.compiled.len()).position(|index| {
+            matches!(self.find_pattern_at(index, subject, start), Ok(Some(m)) if m.start() == start)
+        })
+    }
+
+    /// Run the regex of the pattern at `index` from `start`, recompiling it without JIT if the
+    /// JIT stack runs out.
+    ///
+    /// Unlike the merged regex, the recompiled regex is not kept: this only happens for
+    /// adversarial user agents, and keeping one per pattern would double the memory use.
+    fn find_pattern_at<'s>(
+        &self,
+        index: usize,
+        subject: &'s [u8],
+        start: usize,
+    ) -> Result<Option<Match<'s>>, Pcre2Error> {
+        let regex = &self.compiled[index].1;
+
+        match regex.find_at(subject, start) {
+            Err(e) if is_jit_stack_error(&e) && !self.injected => {
+                let mut builder = self.options.regex_builder();
+                builder
+                    .jit_if_available(false)
+                    .caseless(self.options.caseless() && !self.entries[index].case_sensitive);
+                builder.build(regex.as_str())?.find_at(subject, start)
+            }
+            result => result,
+        }
+    }
+
+    /// Returns the byte span of every numbered capture group of the merged regex's match.
+    ///
+    /// Index `0` is the whole match; the following indexes are the groups of all patterns,
+    /// numbered in load order since the merged regex is their `|` concatenation. Groups that did
+    /// not participate in the match are `None`.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if the user agent doesn't match.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["(curl)/(\\d+)?".to_string(), "(wget)".to_string()]).unwrap();
+    /// assert_eq!(
+    ///     detector.capture_spans("curl/7.68.0"),
+    ///     Some(vec![Some((0, 6)), Some((0, 4)), Some((5, 6)), None])
+    /// );
+    /// ```
+    #[must_use]
+    pub fn capture_spans(&self, user_agent: &str) -> Option<Vec<Option<(usize, usize)>>> {
+        if user_agent.is_empty() {
+            return None;
+        }
+
+        let subject = self.options.subject(user_agent);
+        let captures = self.regex.captures(subject.as_bytes()).ok()??;
+
+        Some(
+            (0..captures.len())
+                .map(|i| captures.get(i).map(|m| (m.start(), m.end())))
+                .collect(),
+        )
+    }
+
+    /// Classify the user agent, telling apart a user agent that matched no pattern from one that
+    /// could not be checked.
+    ///
+    /// Unlike [`BotDetector::classify`], which reports both as `None`, an empty or blank user
+    /// agent and a matching error (e.g. pcre2's match limit being hit) give
+    /// `Classification::Unknown`.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::{BotDetector, Classification};
+    /// let detector = BotDetector::from_patterns(&["Googlebot".to_string()]).unwrap();
+    /// assert!(matches!(detector.classify_tri("Googlebot/2.1"), Classification::Bot(_)));
+    /// assert_eq!(detector.classify_tri("Mozilla/5.0"), Classification::Human);
+    /// assert_eq!(detector.classify_tri("  "), Classification::Unknown);
+    /// ```
+    #[must_use]
+    pub fn classify_tri(&self, user_agent: &str) -> Classification {
+        if user_agent.trim().is_empty() {
+            return Classification::Unknown;
+        }
+
+        let subject = self.options.subject(user_agent);
+        let matched = match self.find_merged(subject.as_bytes()) {
+            Ok(Some(matched)) => matched,
+            Ok(None) => return Classification::Human,
+            Err(_) => return Classification::Unknown,
+        };
+
+        match self.pattern_index_at(subject.as_bytes(), matched.start()) {
+            Some(index) => {
+                Classification::Bot(bot_info(&self.options, &self.entries[index], &matched))
+            }
+            None => Classification::Unknown,
+        }
+    }
+
+    /// Classify the user agent like [`BotDetector::classify_tri`], taking external signals into
+    /// account for borderline user agents.
+    ///
+    /// The patterns remain the only way to get `Classification::Bot`, and a bot stays a bot
+    /// whatever the signals. The signals can only turn `Human` into `Unknown`:
+    ///
+    /// * a user agent without any browser engine token (see [`BotDetector::looks_automated`])
+    ///   becomes `Unknown` with any signal;
+    /// * a browser-like user agent becomes `Unknown` when both signals are set.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    /// * `signals` - What is known about the request besides its user agent.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::{BotDetector, Classification, Signals};
+    /// let detector = BotDetector::from_patterns(&["Googlebot".to_string()]).unwrap();
+    /// let signals = Signals { datacenter_ip: true, missing_cookie: false };
+    /// assert_eq!(detector.classify_with_signals("my-scraper/0.1", &signals), Classification::Unknown);
+    /// assert_eq!(detector.classify_with_signals("my-scraper/0.1", &Signals::default()), Classification::Human);
+    /// ```
+    #[must_use]
+    pub fn classify_with_signals(&self, user_agent: &str, signals: &Signals) -> Classification {
+        let classification = self.classify_tri(user_agent);
+
+        if classification != Classification::Human {
+            return classification;
+        }
+
+        let suspicious = if self.has_engine_token(user_agent) {
+            signals.datacenter_ip && signals.missing_cookie
+        } else {
+            signals.datacenter_ip || signals.missing_cookie
+        };
+
+        if suspicious {
+            Classification::Unknown
+        } else {
+            Classification::Human
+        }
+    }
+
+    /// Check if the user agent carries the signature of a headless or automated browser, such as
+    /// `HeadlessChrome`, Puppeteer or Playwright.
+    ///
+    /// Headless browsers otherwise look like a regular browser, so they are checked against a
+    /// dedicated signature set, independently from the bot patterns. The bundled set lives in
+    /// `src/headless.json` and lists the tokens these tools put in their default user agents,
+    /// as documented by their projects; it can be replaced with
+    /// [`BotDetectorBuilder::headless_patterns`].
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["Googlebot".to_string()]).unwrap();
+    /// let user_agent = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) HeadlessChrome/120.0.0.0 Safari/537.36";
+    /// assert!(detector.is_headless(user_agent));
+    /// assert!(!detector.is_bot(user_agent));
+    /// ```
+    #[must_use]
+    pub fn is_headless(&self, user_agent: &str) -> bool {
+        self.headless
+            .as_ref()
+            .is_some_and(|headless| headless.is_bot(user_agent))
+    }
+
+    /// Check if the user agent is a well-known good bot, such as a search engine crawler, to be
+    /// allowed rather than blocked.
+    ///
+    /// The user agent is a good bot when the name it is classified with by
+    /// [`BotDetector::name_of_match`], i.e. the `name` of the matching entry or else the matched
+    /// text, is one of the good bot names, ignoring ASCII case. Naming the entries of the good
+    /// bots therefore makes the check reliable whatever their patterns. The bundled names live in
+    /// `src/good_bots.json` (`Googlebot`, `Bingbot`, `DuckDuckBot`...) and can be replaced
+    /// with [`BotDetectorBuilder::good_bots`].
+    ///
+    /// The user agent alone can be spoofed, so a good bot should still be verified, e.g. by a
+    /// reverse DNS lookup, before being trusted.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["Googlebot".to_string(), "scrapy".to_string()]).unwrap();
+    /// assert!(detector.is_good_bot("Mozilla/5.0 (compatible; Googlebot/2.1)"));
+    /// assert!(!detector.is_good_bot("Scrapy/2.11"));
+    /// assert!(detector.is_bad_bot("Scrapy/2.11"));
+    /// ```
+    #[must_use]
+    pub fn is_good_bot(&self, user_agent: &str) -> bool {
+        self.name_of_match(user_agent)
+            .is_some_and(|name| self.is_good_name(&name))
+    }
+
+    /// Check if the user agent is a bot that is not a good bot, see
+    /// [`BotDetector::is_good_bot`].
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    #[must_use]
+    pub fn is_bad_bot(&self, user_agent: &str) -> bool {
+        self.name_of_match(user_agent)
+            .is_some_and(|name| !self.is_good_name(&name))
+    }
+
+    fn is_good_name(&self, name: &str) -> bool {
+        self.options
+            .good_bots()
+            .iter()
+            .any(|good| good.eq_ignore_ascii_case(name))
+    }
+
+    /// Classify the value of a `Sec-CH-UA` client hints header.
+    ///
+    /// The header is checked against the patterns set with
+    /// [`BotDetectorBuilder::client_hint_patterns`], independently from the user agent
+    /// patterns. Returns `None` if the detector has no client hint patterns.
+    ///
+    /// # Arguments
+    ///
+    /// * `sec_ch_ua` - The value of the `Sec-CH-UA` header.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(BotInfo)` if the header matches a client hint pattern, otherwise `None`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::builder()
+    ///     .patterns(&["Googlebot".to_string()])
+    ///     .client_hint_patterns(&["HeadlessChrome".to_string()])
+    ///     .build()
+    ///     .unwrap();
+    /// let header = r#""Chromium";v="118", "HeadlessChrome";v="118", "Not=A?Brand";v="99""#;
+    /// assert_eq!(detector.check_client_hints(header).unwrap().pattern, "HeadlessChrome");
+    /// assert!(!detector.is_bot(header));
+    /// ```
+    #[must_use]
+    pub fn check_client_hints(&self, sec_ch_ua: &str) -> Option<BotInfo> {
+        self.client_hints.as_ref()?.classify(sec_ch_ua)
+    }
+
+    /// Classify the user agent, unless it matches one of the exclusion patterns.
+    ///
+    /// Exclusions take precedence: a user agent matching an exclusion is reported as `None` even
+    /// if it matches bot patterns, so an allowlisted client is never classified as a bot. Without
+    /// exclusions, this is the same as [`BotDetector::classify`].
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::builder()
+    ///     .patterns(&["bot".to_string()])
+    ///     .exclusions(&["our-uptime-bot".to_string()])
+    ///     .build()
+    ///     .unwrap();
+    /// assert!(detector.classify_filtered("our-uptime-bot/1.0").is_none());
+    /// assert!(detector.classify_filtered("otherbot/1.0").is_some());
+    /// ```
+    #[must_use]
+    pub fn classify_filtered(&self, user_agent: &str) -> Option<BotInfo> {
+        if self.is_excluded(user_agent) {
+            return None;
+        }
+
+        self.classify(user_agent)
+    }
+
+    /// Check if the user agent matches one of the exclusion patterns.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    #[must_use]
+    pub fn is_excluded(&self, user_agent: &str) -> bool {
+        self.exclusion.as_ref().is_some_and(|exclusion| {
+            exclusion
+                .is_match(self.options.subject(user_agent).as_bytes())
+                .unwrap_or(false)
+        })
+    }
+
+    /// Classify the user agent by the first pattern, in load order, that matches it.
+    ///
+    /// [`BotDetector::classify`] reports the leftmost match of the merged regex in the user
+    /// agent, so a pattern matching early in the string wins over one listed before it in the
+    /// file. Here the patterns are tried one by one in file order, wherever they match, which
+    /// gives the first entries priority over the following ones. This costs one regex run per
+    /// pattern up to the first hit.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(BotInfo)` if the user agent is a bot, otherwise `None`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["Googlebot".to_string(), "compatible".to_string()]).unwrap();
+    /// let user_agent = "Mozilla/5.0 (compatible; Googlebot/2.1)";
+    /// assert_eq!(detector.classify(user_agent).unwrap().pattern, "compatible");
+    /// assert_eq!(detector.classify_priority(user_agent).unwrap().pattern, "Googlebot");
+    /// ```
+    #[must_use]
+    pub fn classify_priority(&self, user_agent: &str) -> Option<BotInfo> {
+        if user_agent.is_empty() {
+            return None;
+        }
+
+        let subject = self.options.subject(user_agent);
+        let (entry, matched) = self.pattern_matches(subject.as_bytes()).next()?;
+
+        Some(bot_info(&self.options, entry, &matched))
+    }
+
+    /// Classify the user agent against every pattern on its own, returning one result per
+    /// matching pattern in load order.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["bot".to_string(), "Googlebot".to_string()]).unwrap();
+    /// assert_eq!(detector.classify_all("Googlebot/2.1").len(), 2);
+    /// ```
+    #[must_use]
+    pub fn classify_all(&self, user_agent: &str) -> Vec<BotInfo> {
+        if user_agent.is_empty() {
+            return Vec::new();
+        }
+
+        let subject = self.options.subject(user_agent);
+
+        self.pattern_matches(subject.as_bytes())
+            .map(|(entry, matched)| bot_info(&self.options, entry, &matched))
+            .collect()
+    }
+
+    /// Replace every part of the user agent matched by the patterns with the output of `f`,
+    /// e.g. to log user agents without the raw bot tokens but still correlate them by hash.
+    ///
+    /// The matches are those of the merged regex, scanned from left to right without overlap;
+    /// empty matches are left alone. A match is widened to whole characters, so `f` always
+    /// receives, and the output always is, valid UTF-8. The output is the user agent as
+    /// prepared for matching, so options rewriting it, such as
+    /// [`BotDetectorBuilder::strip_quotes`] or [`BotDetectorBuilder::precompute_lowercase`],
+    /// show in it. Scanning stops at the first match error, keeping the rest as is.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be redacted.
+    /// * `f` - Returns the replacement of each matched token.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["Googlebot".to_string()]).unwrap();
+    /// let redacted = detector.redact_with("Mozilla/5.0 (compatible; Googlebot/2.1)", |token| {
+    ///     format!("<{}>", token.len())
+    /// });
+    /// assert_eq!(redacted, "Mozilla/5.0 (compatible; <9>/2.1)");
+    /// ```
+    #[must_use]
+    pub fn redact_with(&self, user_agent: &str, mut f: impl FnMut(&str) -> String) -> String {
+        let subject = self.options.subject(user_agent);
+        let mut redacted = String::with_capacity(subject.len());
+        let mut last = 0;
+
+        for matched in self
+            .regex
+            .find_iter(subject.as_bytes())
+            .map_while(Result::ok)
+        {
+            if matched.start() < last || matched.start() == matched.end() {
+                continue;
+            }
+
+            let mut start = matched.start();
+            while !subject.is_char_boundary(start) {
+                start -= 1;
+            }
+            let mut end = matched.end();
+            while !subject.is_char_boundary(end) {
+                end += 1;
+            }
+
+            redacted.push_str(&subject[last..start]);
+            redacted.push_str(&f(&subject[start..end]));
+            last = end;
+        }
+
+        redacted.push_str(&subject[last..]);
+        redacted
+    }
+
+    /// Returns the distinct names of the bots matching the user agent.
+    ///
+    /// Every pattern matching the user agent contributes the name of its entry, so several
+    /// signatures of the same bot, e.g. `Google` and `Googlebot` both named `Googlebot`,
+    /// collapse into a single name. An entry without a name contributes its pattern instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Returns
+    ///
+    /// Returns the set of names, empty if the user agent doesn't match.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let json = r#"[
+    ///     { "pattern": "Google", "name": "Googlebot" },
+    ///     { "pattern": "Googlebot", "name": "Googlebot" }
+    /// ]"#;
+    /// let detector = BotDetector::from_json_bytes(json.as_bytes()).unwrap();
+    /// assert_eq!(detector.matched_names("Googlebot/2.1").len(), 1);
+    /// ```
+    #[must_use]
+    pub fn matched_names(&self, user_agent: &str) -> HashSet<String> {
+        if user_agent.is_empty() {
+            return HashSet::new();
+        }
+
+        let subject = self.options.subject(user_agent);
+
+        self.pattern_matches(subject.as_bytes())
+            .map(|(entry, _)| entry.name.clone().unwrap_or_else(|| entry.pattern.clone()))
+            .collect()
+    }
+
+    /// Same as [`BotDetector::classify_all`], with the most specific results first.
+    ///
+    /// Results are sorted by descending length of the matched substring, before any
+    /// `max_match_len` cut. Ties keep load order, so of two patterns matching equally long
+    /// substrings, the one listed first in the file comes first.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["bot".to_string(), "Googlebot".to_string()]).unwrap();
+    /// assert_eq!(detector.classify_all_ranked("Googlebot/2.1")[0].pattern, "Googlebot");
+    /// ```
+    #[must_use]
+    pub fn classify_all_ranked(&self, user_agent: &str) -> Vec<BotInfo> {
+        if user_agent.is_empty() {
+            return Vec::new();
+        }
+
+        let subject = self.options.subject(user_agent);
+        let mut matches: Vec<_> = self.pattern_matches(subject.as_bytes()).collect();
+
+        // a stable sort, so equal lengths stay in load order
+        matches.sort_by_key(|(_, matched)| std::cmp::Reverse(matched.as_bytes().len()));
+
+        matches
+            .into_iter()
+            .map(|(entry, matched)| bot_info(&self.options, entry, &matched))
+            .collect()
+    }
+
+    /// Classify the user agent by the pattern whose own match spans the most bytes, as the most
+    /// specific detection.
+    ///
+    /// This is the first result of [`BotDetector::classify_all_ranked`] without collecting the
+    /// others: every pattern is run, and ties go to the pattern listed first in the file.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(BotInfo)` if the user agent is a bot, otherwise `None`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["bot".to_string(), "Googlebot".to_string()]).unwrap();
+    /// assert_eq!(detector.longest_match("Googlebot/2.1").unwrap().pattern, "Googlebot");
+    /// ```
+    #[must_use]
+    pub fn longest_match(&self, user_agent: &str) -> Option<BotInfo> {
+        if user_agent.is_empty() {
+            return None;
+        }
+
+        let subject = self.options.subject(user_agent);
+
+        // `min_by_key` keeps the first of equal elements, so ties stay in load order
+        self.pattern_matches(subject.as_bytes())
+            .min_by_key(|(_, matched)| std::cmp::Reverse(matched.as_bytes().len()))
+            .map(|(entry, matched)| bot_info(&self.options, entry, &matched))
+    }
+
+    /// Find where each pattern matches the user agent on its own.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `(pattern, start_offset)` pair for every matching pattern, in load order, where
+    /// `start_offset` is the byte offset of the pattern's first match in the user agent.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["bot".to_string(), "Google".to_string()]).unwrap();
+    /// assert_eq!(
+    ///     detector.matches_with_positions("Googlebot/2.1"),
+    ///     vec![("bot".to_string(), 6), ("Google".to_string(), 0)]
+    /// );
+    /// ```
+    #[must_use]
+    pub fn matches_with_positions(&self, user_agent: &str) -> Vec<(String, usize)> {
+        if user_agent.is_empty() {
+            return Vec::new();
+        }
+
+        let subject = self.options.subject(user_agent);
+
+        self.pattern_matches(subject.as_bytes())
+            .map(|(entry, matched)| (entry.pattern.clone(), matched.start()))
+            .collect()
+    }
+
+    /// Returns the patterns that do not match the user agent on their own, in load order.
+    ///
+    /// Together with [`BotDetector::matches_with_positions`] this partitions the pattern set,
+    /// which helps pattern authors looking into a user agent that should have been detected.
+    /// An empty user agent matches no pattern, so every pattern is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["bot".to_string(), "curl".to_string()]).unwrap();
+    /// assert_eq!(detector.non_matching_patterns("Googlebot/2.1"), vec!["curl".to_string()]);
+    /// ```
+    #[must_use]
+    pub fn non_matching_patterns(&self, user_agent: &str) -> Vec<String> {
+        let subject = self.options.subject(user_agent);
+
+        // the complement of `pattern_matches`, a match error counting as no match in both
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| {
+                user_agent.is_empty()
+                    || !matches!(
+                        self.find_pattern_at(index, subject.as_bytes(), 0),
+                        Ok(Some(_))
+                    )
+            })
+            .map(|(_, entry)| entry.pattern.clone())
+            .collect()
+    }
+
+    /// Check if the user agent is a bot or merely looks like an automated client.
+    ///
+    /// Besides the patterns, this flags a user agent that doesn't contain any browser engine
+    /// token (see [`DEFAULT_ENGINE_TOKENS`]), which catches custom scripts missing from the
+    /// signature lists. An empty user agent carries no engine token and is flagged as well.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["Googlebot".to_string()]).unwrap();
+    /// assert!(detector.looks_automated("my-scraper/0.1"));
+    /// assert!(!detector.looks_automated("Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0"));
+    /// ```
+    #[must_use]
+    pub fn looks_automated(&self, user_agent: &str) -> bool {
+        self.is_bot(user_agent) || !self.has_engine_token(user_agent)
+    }
+
+    /// Check if the user agent contains one of the browser engine tokens.
+    fn has_engine_token(&self, user_agent: &str) -> bool {
+        let user_agent = user_agent.to_ascii_lowercase();
+
+        self.options
+            .engine_tokens
+            .iter()
+            .any(|token| user_agent.contains(&token.to_ascii_lowercase()))
+    }
+
+    /// Check if the user agent is a suspiciously minimal client, such as a bare HTTP library.
+    ///
+    /// This is a heuristic independent of the patterns: a user agent is flagged when it is
+    /// shorter than [`MINIMAL_CLIENT_MAX_LEN`] bytes once trimmed, contains no browser engine
+    /// token (see [`BotDetectorBuilder::engine_tokens`]), and consists of a single
+    /// `tool/version` token, where the tool name is made of ASCII letters, digits, `-`, `_` and
+    /// `.`, and the version starts with a digit. `curl/7.68.0` and `python-requests/2.25` are
+    /// flagged; `curl`, `my tool/1.0` and browser user agents are not.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["Googlebot".to_string()]).unwrap();
+    /// assert!(detector.is_minimal_client("python-requests/2.25"));
+    /// assert!(!detector.is_minimal_client("Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0"));
+    /// ```
+    #[must_use]
+    pub fn is_minimal_client(&self, user_agent: &str) -> bool {
+        let user_agent = user_agent.trim();
+
+        user_agent.len() < MINIMAL_CLIENT_MAX_LEN
+            && is_tool_version(user_agent)
+            && !self.has_engine_token(user_agent)
+    }
+
+    /// Count how many patterns match the user agent on their own.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["bot".to_string(), "Google".to_string()]).unwrap();
+    /// assert_eq!(detector.match_count("Googlebot/2.1"), 2);
+    /// ```
+    #[must_use]
+    pub fn match_count(&self, user_agent: &str) -> usize {
+        if user_agent.is_empty() {
+            return 0;
+        }
+
+        let subject = self.options.subject(user_agent);
+
+        self.pattern_matches(subject.as_bytes()).count()
+    }
+
+    /// Sum the weights of the patterns matching the user agent on their own.
+    ///
+    /// Entries without an explicit weight count for `1.0`, so with a plain list of patterns the
+    /// score is [`BotDetector::match_count`].
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// # use botagent::pattern::PatternEntry;
+    /// let detector = BotDetector::from_entries(&[
+    ///     PatternEntry::from("bot".to_string()),
+    ///     PatternEntry { pattern: "Google".to_string(), weight: 0.5, ..PatternEntry::default() },
+    /// ])
+    /// .unwrap();
+    /// assert!((detector.score("Googlebot/2.1") - 1.5).abs() < f32::EPSILON);
+    /// ```
+    #[must_use]
+    pub fn score(&self, user_agent: &str) -> f32 {
+        if user_agent.is_empty() {
+            return 0.0;
+        }
+
+        let subject = self.options.subject(user_agent);
+
+        self.pattern_matches(subject.as_bytes())
+            .map(|(entry, _)| entry.weight)
+            .sum()
+    }
+
+    /// Check if the score of the user agent exceeds `threshold`, see [`BotDetector::score`].
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    /// * `threshold` - The score to be exceeded.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["bot".to_string(), "Google".to_string()]).unwrap();
+    /// assert!(detector.is_bot_with_threshold("Googlebot/2.1", 1.5));
+    /// assert!(!detector.is_bot_with_threshold("mybot/1.0", 1.5));
+    /// ```
+    #[must_use]
+    pub fn is_bot_with_threshold(&self, user_agent: &str, threshold: f32) -> bool {
+        self.score(user_agent) > threshold
+    }
+
+    /// Check if at least `min` patterns match the user agent.
+    ///
+    /// Requiring several signals to agree reduces false positives with broad pattern sets, where
+    /// a single weak match shouldn't be enough. A `min` of `0` is always satisfied. This is
+    /// [`BotDetector::has_at_least_matches`], which stops at the `min`th match.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    /// * `min` - The number of matching patterns required.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["bot".to_string(), "Google".to_string()]).unwrap();
+    /// assert!(detector.is_bot_with_min_matches("Googlebot/2.1", 2));
+    /// assert!(!detector.is_bot_with_min_matches("mybot/1.0", 2));
+    /// ```
+    #[must_use]
+    pub fn is_bot_with_min_matches(&self, user_agent: &str, min: usize) -> bool {
+        self.has_at_least_matches(user_agent, min)
+    }
+
+    /// Check if at least `n` patterns match the user agent on their own, stopping as soon as
+    /// the `n`th match is found.
+    ///
+    /// The per-pattern regexes are tried in load order, so this is cheaper than
+    /// `match_count(user_agent) >= n` on large pattern sets, especially for a small `n`.
+    /// An `n` of `0` is always satisfied.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - The user agent string to be checked.
+    /// * `n` - The number of matching patterns required.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["bot".to_string(), "Google".to_string(), "curl".to_string()]).unwrap();
+    /// assert!(detector.has_at_least_matches("Googlebot/2.1", 2));
+    /// assert!(!detector.has_at_least_matches("Googlebot/2.1", 3));
+    /// ```
+    #[must_use]
+    pub fn has_at_least_matches(&self, user_agent: &str, n: usize) -> bool {
+        if n == 0 {
+            return true;
+        }
+
+        if user_agent.is_empty() {
+            return false;
+        }
+
+        let subject = self.options.subject(user_agent);
+
+        self.pattern_matches(subject.as_bytes()).take(n).count() == n
+    }
+
+    /// Check a user agent read from a stream, reading at most `limit` bytes.
+    ///
+    /// pcre2 cannot match across chunks, so the input is buffered, but never beyond `limit`:
+    /// this bounds memory for untrusted streamed input. Anything past the limit is left unread
+    /// and not checked. Bytes that are not valid UTF-8 are replaced before matching.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The stream holding the user agent.
+    /// * `limit` - The maximum number of bytes to read.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BotDetectorError::Io` if reading from the stream fails.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["Googlebot".to_string()]).unwrap();
+    /// let body = "Mozilla/5.0 (compatible; Googlebot/2.1)".as_bytes();
+    /// assert!(detector.is_bot_reader(body, 1024).unwrap());
+    /// assert!(!detector.is_bot_reader(body, 16).unwrap());
+    /// ```
+    pub fn is_bot_reader<R: Read>(
+        &self,
+        reader: R,
+        limit: usize,
+    ) -> Result<bool, BotDetectorError> {
+        let mut buffer = Vec::new();
+        reader
+            .take(u64::try_from(limit).unwrap_or(u64::MAX))
+            .read_to_end(&mut buffer)?;
+
+        Ok(self.is_bot(&String::from_utf8_lossy(&buffer)))
+    }
+
+    /// Scan a log line by line, yielding the bot lines with their classification.
+    ///
+    /// Lines are numbered from 1. With `ua_column`, each line is split into columns and only
+    /// the column at that index (from 0) is checked: lines containing a tab are split on tabs
+    /// (TSV), other lines on commas (CSV), where a double-quoted field may contain commas and
+    /// `""` escapes. Without it, the whole line is checked. Lines that are not valid UTF-8 or
+    /// lack the column are skipped, and scanning stops at the first read error.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The log to be scanned.
+    /// * `ua_column` - The index of the user agent column, or `None` to check whole lines.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["Googlebot".to_string()]).unwrap();
+    /// let log = "GET,/,\"Mozilla/5.0 (X11; Linux x86_64)\"\nGET,/,\"Mozilla/5.0 (compatible; Googlebot/2.1)\"\n";
+    /// let hits: Vec<_> = detector.scan_reader(log.as_bytes(), Some(2)).collect();
+    /// assert_eq!(hits.len(), 1);
+    /// assert_eq!(hits[0].0, 2);
+    /// ```
+    pub fn scan_reader<'a, R: BufRead + 'a>(
+        &'a self,
+        reader: R,
+        ua_column: Option<usize>,
+    ) -> impl Iterator<Item = (usize, BotInfo)> + 'a {
+        reader
+            .split(b'\n')
+            .map_while(Result::ok)
+            .enumerate()
+            .filter_map(move |(index, line)| {
+                let line = String::from_utf8(line).ok()?;
+                let line = line.strip_suffix('\r').unwrap_or(&line);
+                let user_agent = match ua_column {
+                    Some(column) => log_column(line, column)?,
+                    None => Cow::Borrowed(line),
+                };
+
+                Some((index + 1, self.classify(&user_agent)?))
+            })
+    }
+
+    /// Classify every line of a log, counting the bot lines in total and per bot name.
+    ///
+    /// Each whole line, without its `\n` or `\r\n` ending, is checked as a user agent, as
+    /// [`BotDetector::scan_reader`] does without a column. Lines that are not valid UTF-8 are
+    /// counted in `total_lines` only, and reading stops at the first read error. Bots are counted
+    /// under the name of the entry they were classified by, or its pattern if it has none.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The log to be summarized, one user agent per line.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["Googlebot".to_string(), "curl".to_string()]).unwrap();
+    /// let log = "Googlebot/2.1\nMozilla/5.0 (X11; Linux x86_64)\ncurl/7.68.0\nGooglebot/2.1\n";
+    /// let summary = detector.summarize_log(log.as_bytes());
+    /// assert_eq!(summary.total_lines, 4);
+    /// assert_eq!(summary.bot_lines, 3);
+    /// assert_eq!(summary.by_name["Googlebot"], 2);
+    /// ```
+    #[must_use]
+    pub fn summarize_log<R: BufRead>(&self, reader: R) -> LogSummary {
+        let mut summary = LogSummary::default();
+
+        for line in reader.split(b'\n').map_while(Result::ok) {
+            summary.total_lines += 1;
+
+            let Ok(line) = String::from_utf8(line) else {
+                continue;
+            };
+            let line = line.strip_suffix('\r').unwrap_or(&line);
+
+            if let Some(info) = self.classify(line) {
+                summary.bot_lines += 1;
+                *summary
+                    .by_name
+                    .entry(info.name.unwrap_or(info.pattern))
+                    .or_insert(0) += 1;
+            }
+        }
+
+        summary
+    }
+
+    /// Find patterns that look redundant on a sample of user agents.
+    ///
+    /// Returns the pairs `(a, b)` where pattern `a` matched every sample that pattern `b`
+    /// matched, `b` matching at least one. This is an approximation based on the samples only,
+    /// not a proof that `a` matches a superset of `b`: a pair is a hint to review `b`, and the
+    /// more varied the samples, the more meaningful the report. Two patterns matching the same
+    /// samples are reported both ways.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - The user agents to be checked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["bot".to_string(), "Googlebot".to_string()]).unwrap();
+    /// let report = detector.redundancy_report(&["Googlebot/2.1", "mybot/1.0", "Mozilla/5.0"]);
+    /// assert_eq!(report, vec![("bot".to_string(), "Googlebot".to_string())]);
+    /// ```
+    #[must_use]
+    pub fn redundancy_report(&self, samples: &[&str]) -> Vec<(String, String)> {
+        let subjects: Vec<_> = samples
+            .iter()
+            .filter(|sample| !sample.is_empty())
+            .map(|sample| self.options.subject(sample))
+            .collect();
+        let matched: Vec<Vec<bool>> = (0..self.entries.len())
+            .map(|index| {
+                subjects
+                    .iter()
+                    .map(|subject| {
+                        matches!(
+                            self.find_pattern_at(index, subject.as_bytes(), 0),
+                            Ok(Some(_))
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut report = Vec::new();
+
+        for (b, b_matched) in matched.iter().enumerate() {
+            if !b_matched.contains(&true) {
+                continue;
+            }
+
+            for (a, a_matched) in matched.iter().enumerate() {
+                let covers = a_matched.iter().zip(b_matched).all(|(&a, &b)| a || !b);
+
+                if a != b && covers {
+                    report.push((
+                        self.entries[a].pattern.clone(),
+                        self.entries[b].pattern.clone(),
+                    ));
+                }
+            }
+        }
+
+        report
+    }
+
+    /// Run every per-pattern regex against the subject, yielding the matching entries in load
+    /// order together with their match.
+    fn pattern_matches<'s>(
+        &'s self,
+        subject: &'s [u8],
+    ) -> impl Iterator<Item = (&'s PatternEntry, Match<'s>)> + 's {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(move |(index, entry)| {
+                Some((entry, self.find_pattern_at(index, subject, 0).ok()??))
+            })
+    }
+
+    /// Returns the `|`-joined pattern the detector compiled its merged regex from.
+    ///
+    /// This is the exact string handed to pcre2, e.g. lowercased when `precompute_lowercase` is
+    /// enabled, so it can be pasted into a regex tester to reproduce a match. Returns `None` for
+    /// a detector built with [`BotDetector::from_regex`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["Googlebot".to_string(), "curl".to_string()]).unwrap();
+    /// assert_eq!(detector.merged_pattern(), Some("Googlebot|curl"));
+    /// ```
+    #[must_use]
+    pub fn merged_pattern(&self) -> Option<&str> {
+        if self.injected {
+            None
+        } else {
+            Some(self.regex.as_str())
+        }
+    }
+
+    /// Returns the patterns the detector was built from, in load order.
+    #[must_use]
+    pub fn patterns(&self) -> Vec<&str> {
+        self.entries
+            .iter()
+            .map(|entry| entry.pattern.as_str())
+            .collect()
+    }
+
+    /// Check if the pattern is one of the patterns the detector was built from or extended
+    /// with, e.g. before calling [`BotDetector::add_patterns`].
+    ///
+    /// The pattern strings are compared exactly, without ignoring case or surrounding
+    /// whitespace, so `googlebot` doesn't contain `Googlebot` even though both match the same
+    /// user agents. Blank patterns are dropped when the detector is built, so they are never
+    /// contained.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The pattern string to be looked up.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let mut detector = BotDetector::from_patterns(&["Googlebot".to_string()]).unwrap();
+    /// if !detector.contains_pattern("curl") {
+    ///     detector.add_patterns(&["curl".to_string()]).unwrap();
+    /// }
+    /// assert!(detector.contains_pattern("curl"));
+    /// ```
+    #[must_use]
+    pub fn contains_pattern(&self, pattern: &str) -> bool {
+        self.entries.iter().any(|entry| entry.pattern == pattern)
+    }
+
+    /// Returns each pattern with its regex compiled on its own, in load order, for matching
+    /// loops of your own without recompiling.
+    ///
+    /// The regexes are compiled with the detector's options, e.g. from the lowercased pattern
+    /// when `precompute_lowercase` is enabled, in which case user agents must be lowercased
+    /// before being matched against them. Every detector compiles its patterns on their own,
+    /// so the slice is never empty; a detector built with [`BotDetector::from_regex`] holds
+    /// the given regex as its single entry.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["Googlebot".to_string(), "curl".to_string()]).unwrap();
+    /// let matching: Vec<&str> = detector
+    ///     .pattern_regexes()
+    ///     .iter()
+    ///     .filter(|(_, regex)| regex.is_match(b"curl/7.68.0").unwrap())
+    ///     .map(|(pattern, _)| pattern.as_str())
+    ///     .collect();
+    /// assert_eq!(matching, vec!["curl"]);
+    /// ```
+    #[must_use]
+    pub fn pattern_regexes(&self) -> &[(String, Regex)] {
+        &self.compiled
+    }
+
+    /// Returns the pattern entries the detector was built from, in load order.
+    #[must_use]
+    pub fn entries(&self) -> &[PatternEntry] {
+        &self.entries
+    }
+
+    /// Returns the positions, in the list the detector was built from, of the entries dropped
+    /// because their pattern is empty or only whitespace.
+    ///
+    /// Such a pattern would add an empty branch to the merged regex, matching every user agent.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::from_patterns(&["Googlebot".to_string(), " ".to_string()]).unwrap();
+    /// assert_eq!(detector.dropped_indices(), &[1]);
+    /// assert!(!detector.is_bot("Mozilla/5.0"));
+    /// ```
+    #[must_use]
+    pub fn dropped_indices(&self) -> &[usize] {
+        &self.dropped
+    }
+
+    /// Returns what the detector was loaded with, gathering [`BotDetector::entries`],
+    /// [`BotDetector::dropped_indices`] and [`BotDetector::pattern_version`] in a single value.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::{BotDetector, LoadReport};
+    /// let detector = BotDetector::from_patterns(&["Googlebot".to_string(), " ".to_string()]).unwrap();
+    /// assert_eq!(
+    ///     detector.load_report(),
+    ///     LoadReport { patterns: 1, dropped: vec![1], version: None }
+    /// );
+    /// ```
+    #[must_use]
+    pub fn load_report(&self) -> LoadReport {
+        LoadReport {
+            patterns: self.entries.len(),
+            dropped: self.dropped.clone(),
+            version: self.pattern_version.map(String::from),
+        }
+    }
+
+    /// Count how many of the sample user agents each pattern matches on its own, in load order.
+    ///
+    /// Patterns matching none of a representative sample are candidates for removal. Empty
+    /// samples match no pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - The user agents to match the patterns against.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::{BotDetector, PatternStats};
+    /// let detector = BotDetector::from_patterns(&["bot".to_string(), "curl".to_string()]).unwrap();
+    /// let stats = detector.pattern_stats(&["Googlebot/2.1", "mybot/1.0"]);
+    /// assert_eq!(stats[0], PatternStats { pattern: "bot".to_string(), matches: 2 });
+    /// assert_eq!(stats[1].matches, 0);
+    /// ```
+    #[must_use]
+    pub fn pattern_stats(&self, samples: &[&str]) -> Vec<PatternStats> {
+        let subjects: Vec<_> = samples
+            .iter()
+            .filter(|sample| !sample.is_empty())
+            .map(|sample| self.options.subject(sample))
+            .collect();
+
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| PatternStats {
+                pattern: entry.pattern.clone(),
+                matches: subjects
+                    .iter()
+                    .filter(|subject| {
+                        matches!(
+                            self.find_pattern_at(index, subject.as_bytes(), 0),
+                            Ok(Some(_))
+                        )
+                    })
+                    .count(),
+            })
+            .collect()
+    }
+
+    /// Returns the pattern of the first entry named `name`, compared case-insensitively.
+    ///
+    /// Only entries of the object format carry a name, see [`PatternEntry`].
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The bot name to look up.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// # use botagent::pattern::PatternEntry;
+    /// let detector = BotDetector::from_entries(&[PatternEntry {
+    ///     pattern: "googlebot".to_string(),
+    ///     name: Some("Googlebot".to_string()),
+    ///     ..PatternEntry::default()
+    /// }])
+    /// .unwrap();
+    /// assert_eq!(detector.pattern_for_name("GOOGLEBOT"), Some("googlebot"));
+    /// assert_eq!(detector.pattern_for_name("Bingbot"), None);
+    /// ```
+    #[must_use]
+    pub fn pattern_for_name(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|entry| {
+                entry
+                    .name
+                    .as_ref()
+                    .is_some_and(|entry_name| entry_name.to_lowercase() == name.to_lowercase())
+            })
+            .map(|entry| entry.pattern.as_str())
+    }
+
+    /// Serialize the pattern entries back to the patterns file format.
+    ///
+    /// Entries are written in load order, as bare strings unless they carry an id, a name, a
+    /// category, a weight or the `case_sensitive` flag, so the output can be saved and loaded
+    /// again with [`BotDetector::from_json_path`] to get an equivalent detector with the same
+    /// metadata, including after [`BotDetector::add_patterns`]. Blank patterns skipped on load
+    /// and builder options are not part of the output.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BotDetectorError` if the entries cannot be serialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let mut detector = BotDetector::from_patterns(&["Googlebot".to_string()]).unwrap();
+    /// detector.add_patterns(&["curl".to_string()]).unwrap();
+    /// assert_eq!(detector.to_json().unwrap(), "[\n  \"Googlebot\",\n  \"curl\"\n]");
+    /// ```
+    pub fn to_json(&self) -> Result<String, BotDetectorError> {
+        Ok(serde_json::to_string_pretty(&self.entries)?)
+    }
+
+    /// Append new patterns to the detector and recompile the merged regex.
+    ///
+    /// The merged regex is compiled before anything is replaced, so if compilation fails the
+    /// detector is left untouched. Since this takes `&mut self`, a detector shared behind a
+    /// lock (e.g. `Arc<RwLock<BotDetector>>`) is swapped atomically: readers observe either the
+    /// old or the new pattern set, never a partial one. Empty or whitespace-only patterns are
+    /// skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `patterns` - The patterns to be appended to the existing set.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BotDetectorError::TooManyPatterns` if the new total exceeds the builder's
+    /// `max_patterns`, or a `BotDetectorError` if the merged pattern cannot be compiled.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let mut detector = BotDetector::from_patterns(&["Googlebot".to_string()]).unwrap();
+    /// detector.add_patterns(&["curl".to_string()]).unwrap();
+    /// assert!(detector.is_bot("curl/7.68.0"));
+    /// ```
+    pub fn add_patterns(&mut self, patterns: &[String]) -> Result<(), BotDetectorError> {
+        let patterns: Vec<_> = patterns
+            .iter()
+            .filter(|pattern| !is_blank(pattern))
+            .collect();
+
+        if patterns.is_empty() {
+            return Ok(());
+        }
+
+        self.options
+            .check_count(self.entries.len() + patterns.len())?;
+
+        let added: Vec<_> = patterns
+            .into_iter()
+            .cloned()
+            .map(PatternEntry::from)
+            .collect();
+        let mut merged = self.entries.clone();
+        merged.extend_from_slice(&added);
+
+        let regex = self.options.compile_merged(&merged)?;
+        let compiled = self.options.compile_each(&added)?;
+
+        if self.injected {
+            // the injected regex is replaced by its source compiled with our options
+            self.compiled = Arc::new(self.options.compile_each(&merged)?);
+            self.injected = false;
+        } else {
+            // a clone sharing the regexes keeps them, this one gets its own
+            Arc::make_mut(&mut self.compiled).extend(compiled);
+        }
+
+        self.prefilter = self.options.prefilter(&merged);
+        self.regex = Arc::new(regex);
+        self.entries = merged;
+        self.marked = Arc::default();
+        self.interpreted = Arc::default();
+        self.pattern_version = None;
+
+        Ok(())
+    }
+}
+
+/// A user agent normalized once for several queries against a detector, see
+/// [`BotDetector::prepare`].
+///
+/// It borrows the detector for `'d` and the user agent for `'u`, so it lives no longer than
+/// either. Each method answers the same as the [`BotDetector`] method of the same name called
+/// with the original user agent.
+#[derive(Debug)]
+pub struct PreparedUa<'d, 'u> {
+    detector: &'d BotDetector,
+    // whether the original user agent is empty, which never matches
+    empty: bool,
+    subject: Cow<'u, str>,
+}
+
+impl PreparedUa<'_, '_> {
+    /// See [`BotDetector::is_bot`].
+    #[must_use]
+    pub fn is_bot(&self) -> bool {
+        if self.empty {
+            return self.detector.options.empty_ua_is_bot;
+        }
+
+        self.detector.is_bot_subject(self.subject.as_bytes())
+    }
+
+    /// See [`BotDetector::is_bot_match`].
+    #[must_use]
+    pub fn is_bot_match(&self) -> Option<String> {
+        let matched = self.detector.find_merged(self.subject.as_bytes()).ok()??;
+
+        Some(self.detector.options.matched_text(matched.as_bytes()))
+    }
+
+    /// See [`BotDetector::classify`].
+    #[must_use]
+    pub fn classify(&self) -> Option<BotInfo> {
+        self.explain().map(BotInfo::from)
+    }
+
+    /// See [`BotDetector::explain`].
+    #[must_use]
+    pub fn explain(&self) -> Option<Explanation> {
+        if self.empty {
+            return None;
+        }
+
+        self.detector.explain_subject(self.subject.as_bytes())
+    }
+
+    /// See [`BotDetector::match_count`].
+    #[must_use]
+    pub fn match_count(&self) -> usize {
+        if self.empty {
+            return 0;
+        }
+
+        self.detector
+            .pattern_matches(self.subject.as_bytes())
+            .count()
+    }
+
+    /// See [`BotDetector::score`].
+    #[must_use]
+    pub fn score(&self) -> f32 {
+        if self.empty {
+            return 0.0;
+        }
+
+        self.detector
+            .pattern_matches(self.subject.as_bytes())
+            .map(|(entry, _)| entry.weight)
+            .sum()
+    }
+}
+
+impl TryFrom<Vec<String>> for BotDetector {
+    type Error = BotDetectorError;
+
+    /// Build a detector from a list of patterns, see [`BotDetector::from_patterns`].
+    fn try_from(patterns: Vec<String>) -> Result<Self, Self::Error> {
+        Self::with_options(
+            patterns.into_iter().map(PatternEntry::from).collect(),
+            Options::default(),
+        )
+    }
+}
+
+impl FromIterator<String> for BotDetector {
+    /// Build a detector from an iterator of patterns.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the iterator is empty or the patterns cannot be compiled. Use
+    /// `BotDetector::try_from` to handle those errors.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector: BotDetector = ["Googlebot", "curl"].into_iter().map(String::from).collect();
+    /// assert!(detector.is_bot("curl/7.68.0"));
+    /// ```
+    fn from_iter<I: IntoIterator<Item = String>>(iter: I) -> Self {
+        match Self::try_from(iter.into_iter().collect::<Vec<_>>()) {
+            Ok(detector) => detector,
+            Err(e) => panic!("Error detected: {:?}", e.error_message()),
+        }
+    }
+}
+
+/// Returns the column at `index` of a TSV or CSV log line, see [`BotDetector::scan_reader`].
+fn log_column(line: &str, index: usize) -> Option<Cow<'_, str>> {
+    if line.contains('\t') {
+        return line.split('\t').nth(index).map(Cow::Borrowed);
+    }
+
+    let mut rest = line;
+
+    for _ in 0..index {
+        rest = skip_csv_field(rest)?;
+    }
+
+    match rest.strip_prefix('"') {
+        Some(quoted) => {
+            let end = closing_quote(quoted)?;
+            Some(Cow::Owned(quoted[..end].replace("\"\"", "\"")))
+        }
+        None => Some(Cow::Borrowed(rest.split(',').next().unwrap_or(rest))),
+    }
+}
+
+/// Returns what follows the first CSV field of `line` and its comma, or `None` if it is the
+/// last field or an unterminated quoted field.
+fn skip_csv_field(line: &str) -> Option<&str> {
+    let rest = match line.strip_prefix('"') {
+        Some(quoted) => &quoted[closing_quote(quoted)? + 1..],
+        None => line,
+    };
+
+    rest.split_once(',').map(|(_, rest)| rest)
+}
+
+/// Returns the position of the quote closing a quoted CSV field, skipping `""` escapes.
+fn closing_quote(quoted: &str) -> Option<usize> {
+    let bytes = quoted.as_bytes();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'"' {
+            if bytes.get(i + 1) != Some(&b'"') {
+                return Some(i);
+            }
+            i += 1;
+        }
+        i += 1;
+    }
+
+    None
+}
+
+/// Returns the value of a step that may fail, recording its error in `errors` otherwise.
+fn record<T>(result: Result<T, BotDetectorError>, errors: &mut Vec<BotDetectorError>) -> Option<T> {
+    result.map_err(|e| errors.push(e)).ok()
+}
+
+/// Check if a pattern is empty or only whitespace.
+fn is_blank(pattern: &str) -> bool {
+    pattern.trim().is_empty()
+}
+
+/// Build the classification result of a pattern entry and its match.
+fn bot_info(options: &Options, entry: &PatternEntry, matched: &Match) -> BotInfo {
+    BotInfo {
+        pattern: entry.pattern.clone(),
+        name: entry.name.clone(),
+        category: entry.category.clone(),
+        matched_text: options.matched_text(matched.as_bytes()),
+    }
+}
+
+#[cfg(test)]
+mod features {
+    use super::*;
+    use std::thread;
+
+    fn patterns(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_from_patterns() {
+        let detector = BotDetector::from_patterns(&patterns(&["Googlebot"])).unwrap();
+        let bot_user_agent =
+            "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)";
+
+        assert!(detector.is_bot(bot_user_agent));
+        assert!(!detector.is_bot(""));
+        assert_eq!(
+            detector.is_bot_match(bot_user_agent),
+            Some("Googlebot".to_string())
+        );
+        assert!(matches!(
+            BotDetector::from_patterns(&[]),
+            Err(BotDetectorError::Empty)
+        ));
+    }
+
+    #[test]
+    fn test_add_patterns() {
+        let mut detector = BotDetector::from_patterns(&patterns(&["Googlebot"])).unwrap();
+
+        assert!(!detector.is_bot("curl/7.68.0"));
+
+        detector.add_patterns(&patterns(&["curl"])).unwrap();
+
+        assert!(detector.is_bot("curl/7.68.0"));
+        assert_eq!(detector.patterns(), patterns(&["Googlebot", "curl"]));
+
+        // an invalid pattern leaves the detector untouched
+        assert!(detector.add_patterns(&patterns(&["(unclosed"])).is_err());
+        assert_eq!(detector.patterns().len(), 2);
+        assert!(detector.is_bot("curl/7.68.0"));
+    }
+
+    #[test]
+    fn test_classify() {
+        let detector = BotDetector::from_patterns(&patterns(&["bot", "Googlebot"])).unwrap();
+
+        // the leftmost match wins, then the first pattern matching there
+        assert_eq!(
+            detector.classify("Mozilla/5.0 (compatible; Googlebot/2.1)"),
+            Some(BotInfo {
+                pattern: "Googlebot".to_string(),
+                name: None,
+                category: None,
+                matched_text: "Googlebot".to_string(),
+            })
+        );
+        assert_eq!(detector.classify("Mozilla/5.0"), None);
+        assert_eq!(detector.classify(""), None);
+    }
+
+    #[test]
+    fn test_classify_all_ranked() {
+        let detector =
+            BotDetector::from_patterns(&patterns(&["bot", "compatible", "Googlebot", "google"]))
+                .unwrap();
+        let ranked: Vec<String> = detector
+            .classify_all_ranked("Mozilla/5.0 (compatible; Googlebot/2.1)")
+            .into_iter()
+            .map(|info| info.pattern)
+            .collect();
+
+        // "compatible" and "Googlebot" tie on length and keep load order
+        assert_eq!(
+            ranked,
+            patterns(&["compatible", "Googlebot", "google", "bot"])
+        );
+        assert!(detector.classify_all_ranked("").is_empty());
+    }
+
+    #[test]
+    fn test_matches_with_positions() {
+        let detector =
+            BotDetector::from_patterns(&patterns(&["bot", "compatible", "Googlebot", "curl"]))
+                .unwrap();
+
+        assert_eq!(
+            detector.matches_with_positions("Mozilla/5.0 (compatible; Googlebot/2.1)"),
+            vec![
+                ("bot".to_string(), 31),
+                ("compatible".to_string(), 13),
+                ("Googlebot".to_string(), 25),
+            ]
+        );
+        assert!(detector.matches_with_positions("").is_empty());
+    }
+
+    #[test]
+    fn test_shared_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<BotDetector>();
+
+        let detector =
+            Arc::new(BotDetector::from_patterns(&patterns(&["Googlebot", "curl"])).unwrap());
+        let bot_user_agent = "Mozilla/5.0 (compatible; Googlebot/2.1)";
+        let human_user_agent = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) Firefox/128.0";
+
+        let workers: Vec<_> = (0..8)
+            .map(|_| {
+                let detector = Arc::clone(&detector);
+
+                thread::spawn(move || {
+                    (0..100).all(|_| {
+                        detector.is_bot(bot_user_agent)
+                            && !detector.is_bot(human_user_agent)
+                            && detector.classify(bot_user_agent).map(|info| info.pattern)
+                                == Some("Googlebot".to_string())
+                    })
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            assert!(worker.join().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_is_bot_with_min_matches() {
+        let detector =
+            BotDetector::from_patterns(&patterns(&["bot", "compatible", "Googlebot"])).unwrap();
+        let bot_user_agent = "Mozilla/5.0 (compatible; Googlebot/2.1)";
+
+        assert_eq!(detector.match_count(bot_user_agent), 3);
+        assert!(detector.is_bot_with_min_matches(bot_user_agent, 3));
+        assert!(!detector.is_bot_with_min_matches(bot_user_agent, 4));
+        assert!(!detector.is_bot_with_min_matches("mybot/1.0", 2));
+        assert_eq!(detector.match_count(""), 0);
+    }
+
+    #[test]
+    fn test_has_at_least_matches() {
+        let detector =
+            BotDetector::from_patterns(&patterns(&["bot", "compatible", "Googlebot", "curl"]))
+                .unwrap();
+        let bot_user_agent = "Mozilla/5.0 (compatible; Googlebot/2.1)";
+
+        for n in 0..=5 {
+            assert_eq!(
+                detector.has_at_least_matches(bot_user_agent, n),
+                detector.match_count(bot_user_agent) >= n,
+                "{n}"
+            );
+        }
+        assert!(detector.has_at_least_matches("", 0));
+        assert!(!detector.has_at_least_matches("", 1));
+    }
+
+    #[test]
+    fn test_score() {
+        let detector = BotDetector::from_entries(&[
+            PatternEntry {
+                pattern: "bot".to_string(),
+                weight: 0.25,
+                ..PatternEntry::default()
+            },
+            PatternEntry {
+                pattern: "google".to_string(),
+                weight: 2.0,
+                ..PatternEntry::default()
+            },
+            PatternEntry::from("curl".to_string()),
+        ])
+        .unwrap();
+
+        assert!((detector.score("Googlebot/2.1") - 2.25).abs() < f32::EPSILON);
+        assert!((detector.score("curl/7.68.0") - 1.0).abs() < f32::EPSILON);
+        assert!(detector.score("Mozilla/5.0").abs() < f32::EPSILON);
+        assert!(detector.is_bot_with_threshold("Googlebot/2.1", 2.0));
+        assert!(!detector.is_bot_with_threshold("mybot/1.0", 0.25));
+    }
+
+    #[test]
+    fn test_looks_automated() {
+        let detector = BotDetector::from_patterns(&patterns(&["Googlebot"])).unwrap();
+        let firefox = "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0";
+
+        assert!(detector.looks_automated("Mozilla/5.0 (compatible; Googlebot/2.1)"));
+        assert!(detector.looks_automated("my-scraper/0.1"));
+        assert!(detector.looks_automated(""));
+        assert!(!detector.looks_automated(firefox));
+
+        let detector = BotDetector::builder()
+            .patterns(&patterns(&["Googlebot"]))
+            .engine_tokens(&patterns(&["my-scraper"]))
+            .build()
+            .unwrap();
+
+        assert!(!detector.looks_automated("my-scraper/0.1"));
+        assert!(detector.looks_automated(firefox));
+    }
+
+    #[test]
+    fn test_from_iter() {
+        let detector: BotDetector = patterns(&["Googlebot", "curl"]).into_iter().collect();
+
+        assert!(detector.is_bot("curl/7.68.0"));
+        assert!(BotDetector::try_from(patterns(&["Googlebot"])).is_ok());
+        assert!(matches!(
+            BotDetector::try_from(patterns(&["(unclosed"])),
+            Err(BotDetectorError::RegexCompile(_))
+        ));
+        assert!(matches!(
+            BotDetector::try_from(Vec::new()),
+            Err(BotDetectorError::Empty)
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "Empty pattern list")]
+    fn test_from_iter_panics() {
+        let _: BotDetector = Vec::new().into_iter().collect();
+    }
+
+    #[test]
+    fn test_classify_filtered() {
+        let detector = BotDetector::builder()
+            .patterns(&patterns(&["bot", "curl"]))
+            .exclusions(&patterns(&["Googlebot"]))
+            .build()
+            .unwrap();
+        let google = "Mozilla/5.0 (compatible; Googlebot/2.1)";
+
+        // matches both a bot pattern and an exclusion, the exclusion wins
+        assert!(detector.classify(google).is_some());
+        assert!(detector.is_excluded(google));
+        assert_eq!(detector.classify_filtered(google), None);
+
+        assert_eq!(
+            detector
+                .classify_filtered("curl/7.68.0")
+                .map(|info| info.pattern),
+            Some("curl".to_string())
+        );
+        assert_eq!(detector.classify_filtered("Mozilla/5.0"), None);
+    }
+
+    #[test]
+    fn test_capture_spans() {
+        let detector =
+            BotDetector::from_patterns(&patterns(&["(wget)", "(google)(bot)?", "(curl)"])).unwrap();
+
+        assert_eq!(
+            detector.capture_spans("Mozilla/5.0 (compatible; Googlebot/2.1)"),
+            Some(vec![
+                Some((25, 34)),
+                None,
+                Some((25, 31)),
+                Some((31, 34)),
+                None
+            ])
+        );
+        assert_eq!(detector.capture_spans("Mozilla/5.0"), None);
+    }
+
+    #[test]
+    fn test_scan_reader() {
+        let detector = BotDetector::from_patterns(&patterns(&["Googlebot", "curl"])).unwrap();
+
+        let log = "1.2.3.4\tGET /\tMozilla/5.0 (X11; Linux x86_64)\n\
+                   1.2.3.4\tGET /\tcurl/7.68.0\r\n\
+                   1.2.3.4\tGET /\n\
+                   1.2.3.4\tGET /\tGooglebot/2.1\n";
+        let lines: Vec<usize> = detector
+            .scan_reader(log.as_bytes(), Some(2))
+            .map(|(line, _)| line)
+            .collect();
+        assert_eq!(lines, vec![2, 4]);
+
+        let log = b"\"curl, \"\"the\"\" tool\",1.0\n\xff curl\n\"Googlebot\"\n";
+        let hits: Vec<(usize, BotInfo)> = detector.scan_reader(&log[..], Some(1)).collect();
+        assert!(hits.is_empty());
+
+        let hits: Vec<(usize, String)> = detector
+            .scan_reader(&log[..], Some(0))
+            .map(|(line, info)| (line, info.pattern))
+            .collect();
+        assert_eq!(
+            hits,
+            vec![(1, "curl".to_string()), (3, "Googlebot".to_string())]
+        );
+
+        assert_eq!(
+            log_column("a,\"b, \"\"c\"\"\",d", 1),
+            Some(Cow::Borrowed("b, \"c\""))
+        );
+        assert_eq!(
+            log_column("a,\"b, \"\"c\"\"\",d", 2),
+            Some(Cow::Borrowed("d"))
+        );
+        assert_eq!(log_column("a,b", 2), None);
+        assert_eq!(detector.scan_reader(&b"curl"[..], None).count(), 1);
+    }
+
+    #[test]
+    fn test_pattern_for_name() {
+        let detector = BotDetector::from_entries(&[
+            PatternEntry::from("curl".to_string()),
+            PatternEntry {
+                pattern: "googlebot".to_string(),
+                name: Some("Googlebot".to_string()),
+                ..PatternEntry::default()
+            },
+        ])
+        .unwrap();
+
+        assert_eq!(detector.pattern_for_name("googlebot"), Some("googlebot"));
+        assert_eq!(detector.pattern_for_name("curl"), None);
+    }
+
+    #[test]
+    fn test_check_client_hints() {
+        let detector = BotDetector::builder()
+            .patterns(&patterns(&["Googlebot"]))
+            .client_hint_patterns(&patterns(&["HeadlessChrome"]))
+            .build()
+            .unwrap();
+
+        let header = r#""Chromium";v="118", "HeadlessChrome";v="118""#;
+        assert_eq!(
+            detector.check_client_hints(header).unwrap().matched_text,
+            "HeadlessChrome"
+        );
+        assert_eq!(detector.check_client_hints(r#""Chromium";v="118""#), None);
+        assert_eq!(detector.check_client_hints(r#""Googlebot";v="2""#), None);
+
+        let detector = BotDetector::from_patterns(&patterns(&["HeadlessChrome"])).unwrap();
+        assert_eq!(detector.check_client_hints(header), None);
+    }
+
+    #[test]
+    fn test_to_json() {
+        let detector = BotDetector::from_entries(&[
+            PatternEntry::from("(?<! cu)bots?(?:\\b|_)".to_string()),
+            PatternEntry {
+                pattern: "googlebot".to_string(),
+                id: None,
+                name: Some("Googlebot".to_string()),
+                category: Some("crawler".to_string()),
+                weight: 2.0,
+                case_sensitive: false,
+            },
+            PatternEntry {
+                pattern: "curl".to_string(),
+                weight: 0.5,
+                ..PatternEntry::default()
+            },
+        ])
+        .unwrap();
+
+        let json = detector.to_json().unwrap();
+        assert_eq!(
+            json,
+            r#"[
+  "(?<! cu)bots?(?:\\b|_)",
+  {
+    "pattern": "googlebot",
+    "name": "Googlebot",
+    "category": "crawler",
+    "weight": 2.0
+  },
+  {
+    "pattern": "curl",
+    "weight": 0.5
+  }
+]"#
+        );
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), &json).unwrap();
+        let reloaded = BotDetector::from_json_path(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(reloaded.entries(), detector.entries());
+        assert_eq!(reloaded.merged_pattern(), detector.merged_pattern());
+    }
+
+    #[test]
+    fn test_classify_tri() {
+        let detector = BotDetector::from_patterns(&patterns(&["curl", "Googlebot"])).unwrap();
+
+        assert_eq!(
+            detector.classify_tri("Googlebot/2.1"),
+            Classification::Bot(BotInfo {
+                pattern: "Googlebot".to_string(),
+                name: None,
+                category: None,
+                matched_text: "Googlebot".to_string(),
+            })
+        );
+        assert_eq!(detector.classify_tri("Mozilla/5.0"), Classification::Human);
+        assert_eq!(detector.classify_tri(""), Classification::Unknown);
+        assert_eq!(detector.classify_tri(" \t"), Classification::Unknown);
+
+        // catastrophic backtracking hits pcre2's match limit
+        let detector = BotDetector::from_patterns(&patterns(&["(a+)+$"])).unwrap();
+        let user_agent = format!("{}!", "a".repeat(64));
+        assert_eq!(detector.classify_tri(&user_agent), Classification::Unknown);
+    }
+
+    #[test]
+    fn test_from_json_bytes() {
+        let detector = BotDetector::from_json_bytes(include_bytes!("patterns.json")).unwrap();
+        assert!(detector.is_bot("Mozilla/5.0 (compatible; Googlebot/2.1)"));
+
+        let detector = BotDetector::from_json_bytes(b"\xef\xbb\xbf [\"curl\"]\n").unwrap();
+        assert_eq!(detector.patterns(), vec!["curl"]);
+
+        assert!(matches!(
+            BotDetector::from_json_bytes(b"[\"cu\xffrl\"]"),
+            Err(BotDetectorError::JsonParse(_))
+        ));
+        assert!(matches!(
+            BotDetector::from_json_bytes(b"[\"curl\""),
+            Err(BotDetectorError::JsonParse(_))
+        ));
+    }
+
+    #[test]
+    fn test_max_patterns() {
+        let builder = BotDetector::builder()
+            .patterns(&patterns(&["Googlebot", "curl", "wget"]))
+            .max_patterns(2);
+        assert!(matches!(
+            builder.build(),
+            Err(BotDetectorError::TooManyPatterns { count: 3, limit: 2 })
+        ));
+
+        let mut detector = BotDetector::builder()
+            .patterns(&patterns(&["Googlebot", "curl"]))
+            .max_patterns(2)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            detector.add_patterns(&patterns(&["wget"])),
+            Err(BotDetectorError::TooManyPatterns { count: 3, limit: 2 })
+        ));
+        assert_eq!(detector.patterns(), vec!["Googlebot", "curl"]);
+    }
+
+    #[test]
+    fn test_redundancy_report() {
+        let detector =
+            BotDetector::from_patterns(&patterns(&["bot", "googlebot", "google", "curl", "wget"]))
+                .unwrap();
+        let report = detector.redundancy_report(&[
+            "Googlebot/2.1",
+            "Google-Read-Aloud",
+            "mybot/1.0",
+            "curl/7.68.0",
+            "Mozilla/5.0",
+        ]);
+
+        assert_eq!(
+            report,
+            vec![
+                ("bot".to_string(), "googlebot".to_string()),
+                ("google".to_string(), "googlebot".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_report() {
+        let detector = BotDetector::from_patterns(&patterns(&["Googlebot", "", "curl"])).unwrap();
+        assert_eq!(
+            detector.load_report(),
+            LoadReport {
+                patterns: 2,
+                dropped: vec![1],
+                version: None,
+            }
+        );
+
+        let bundled = BotDetector::with_default_patterns().unwrap();
+        assert_eq!(
+            bundled.load_report().version.as_deref(),
+            bundled.pattern_version()
+        );
+        assert!(bundled.load_report().version.is_some());
+    }
+
+    #[test]
+    fn test_pattern_stats() {
+        let detector =
+            BotDetector::from_patterns(&patterns(&["bot", "googlebot", "wget"])).unwrap();
+        let stats = detector.pattern_stats(&["Googlebot/2.1", "mybot/1.0", "curl/7.68.0", ""]);
+
+        assert_eq!(
+            stats,
+            vec![
+                PatternStats {
+                    pattern: "bot".to_string(),
+                    matches: 2,
+                },
+                PatternStats {
+                    pattern: "googlebot".to_string(),
+                    matches: 1,
+                },
+                PatternStats {
+                    pattern: "wget".to_string(),
+                    matches: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_blank_patterns() {
+        let detector = BotDetector::from_patterns(&patterns(&["Googlebot", ""])).unwrap();
+        assert!(!detector.is_bot("Mozilla/5.0 (Windows NT 10.0; Win64; x64)"));
+        assert_eq!(detector.dropped_indices(), &[1]);
+        assert_eq!(detector.merged_pattern(), Some("Googlebot"));
+
+        let mut detector = BotDetector::from_patterns(&patterns(&[" ", "curl", "\t"])).unwrap();
+        assert_eq!(detector.dropped_indices(), &[0, 2]);
+        detector.add_patterns(&patterns(&["wget", ""])).unwrap();
+        assert_eq!(detector.patterns(), vec!["curl", "wget"]);
+        assert!(!detector.is_bot("Mozilla/5.0"));
+
+        assert!(matches!(
+            BotDetector::from_patterns(&patterns(&["", " "])),
+            Err(BotDetectorError::Empty)
+        ));
+    }
+
+    #[test]
+    fn test_classify_priority() {
+        let detector = BotDetector::from_patterns(&patterns(&["curl", "bot", "Mozilla"])).unwrap();
+        let user_agent = "Mozilla/5.0 (compatible; Googlebot/2.1)";
+
+        assert_eq!(detector.classify(user_agent).unwrap().pattern, "Mozilla");
+        assert_eq!(
+            detector.classify_priority(user_agent).unwrap(),
+            BotInfo {
+                pattern: "bot".to_string(),
+                name: None,
+                category: None,
+                matched_text: "bot".to_string(),
+            }
+        );
+        assert_eq!(detector.classify_priority("Safari/537.36"), None);
+    }
+
+    #[test]
+    fn test_classify_with_signals() {
+        let detector = BotDetector::from_patterns(&patterns(&["Googlebot"])).unwrap();
+        let browser = "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0";
+        let datacenter = Signals {
+            datacenter_ip: true,
+            ..Signals::default()
+        };
+        let both = Signals {
+            datacenter_ip: true,
+            missing_cookie: true,
+        };
+
+        assert!(matches!(
+            detector.classify_with_signals("Googlebot/2.1", &Signals::default()),
+            Classification::Bot(_)
+        ));
+        assert!(matches!(
+            detector.classify_with_signals("Googlebot/2.1", &both),
+            Classification::Bot(_)
+        ));
+        assert_eq!(
+            detector.classify_with_signals("my-scraper/0.1", &datacenter),
+            Classification::Unknown
+        );
+        assert_eq!(
+            detector.classify_with_signals(browser, &datacenter),
+            Classification::Human
+        );
+        assert_eq!(
+            detector.classify_with_signals(browser, &both),
+            Classification::Unknown
+        );
+        assert_eq!(
+            detector.classify_with_signals("", &Signals::default()),
+            Classification::Unknown
+        );
+    }
+
+    #[test]
+    fn test_bot_info_display() {
+        let info = BotInfo {
+            pattern: "googlebot".to_string(),
+            name: Some("Googlebot".to_string()),
+            category: None,
+            matched_text: "Googlebot".to_string(),
+        };
+        assert_eq!(
+            info.to_string(),
+            r#"Googlebot (pattern=googlebot) matched "Googlebot""#
+        );
+
+        let info = BotInfo {
+            category: Some("crawler".to_string()),
+            ..info
+        };
+        assert_eq!(
+            info.to_string(),
+            r#"Googlebot (category=crawler, pattern=googlebot) matched "Googlebot""#
+        );
+
+        let info = BotInfo {
+            pattern: "curl.*".to_string(),
+            name: None,
+            category: None,
+            matched_text: "curl \"x\"".to_string(),
+        };
+        assert_eq!(info.to_string(), r#"curl.* matched "curl \"x\"""#);
+    }
+
+    #[test]
+    fn test_is_headless() {
+        let chrome = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+        let headless = chrome.replace("Chrome/", "HeadlessChrome/");
+
+        let detector = BotDetector::from_patterns(&patterns(&["Googlebot"])).unwrap();
+        assert!(detector.is_headless(&headless));
+        assert!(!detector.is_headless(chrome));
+        assert!(BotDetector::from_regex(Regex::new("curl").unwrap()).is_headless(&headless));
+
+        let detector = BotDetector::builder()
+            .patterns(&patterns(&["Googlebot"]))
+            .headless_patterns(&patterns(&["my-automation"]))
+            .build()
+            .unwrap();
+        assert!(detector.is_headless("my-automation/1.0"));
+        assert!(!detector.is_headless(&headless));
+
+        let detector = BotDetector::builder()
+            .patterns(&patterns(&["Googlebot"]))
+            .headless_patterns(&[])
+            .build()
+            .unwrap();
+        assert!(!detector.is_headless(&headless));
+    }
+
+    #[test]
+    fn test_longest_match() {
+        let detector =
+            BotDetector::from_patterns(&patterns(&["bot", "google", "Mozilla/5", "Googlebot"]))
+                .unwrap();
+        let user_agent = "Mozilla/5.0 (compatible; Googlebot/2.1)";
+
+        // "Mozilla/5" and "Googlebot" are both the longest, the first listed wins
+        assert_eq!(
+            detector.longest_match(user_agent).unwrap().pattern,
+            "Mozilla/5"
+        );
+        assert_eq!(detector.longest_match("mybot/1.0").unwrap().pattern, "bot");
+        assert_eq!(detector.longest_match("Safari/537.36"), None);
+    }
+
+    #[test]
+    fn test_match_group() {
+        let detector =
+            BotDetector::from_patterns(&patterns(&["(curl)/(\\d+)?", "compatible; (\\w+)"]))
+                .unwrap();
+
+        // groups are numbered within the matching pattern
+        let user_agent = "Mozilla/5.0 (compatible; Googlebot/2.1)";
+        assert_eq!(
+            detector.match_group(user_agent, 1),
+            Some("Googlebot".to_string())
+        );
+        assert_eq!(detector.match_group(user_agent, 2), None);
+        assert_eq!(
+            detector.match_group("curl/7.68.0", 2),
+            Some("7".to_string())
+        );
+        assert_eq!(detector.match_group("curl/", 2), None);
+        assert_eq!(detector.match_group("Mozilla/5.0", 0), None);
+    }
+
+    #[test]
+    fn test_category_histogram() {
+        let crawler = |pattern: &str| PatternEntry {
+            pattern: pattern.to_string(),
+            category: Some("crawler".to_string()),
+            ..PatternEntry::default()
+        };
+        let detector = BotDetector::from_entries(&[
+            crawler("Googlebot"),
+            crawler("bingbot"),
+            PatternEntry {
+                pattern: "scrapy".to_string(),
+                category: Some("scraper".to_string()),
+                ..PatternEntry::default()
+            },
+            PatternEntry::from("curl".to_string()),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            detector.detect_category("Googlebot/2.1"),
+            Some("crawler".to_string())
+        );
+        assert_eq!(detector.detect_category("curl/7.68.0"), None);
+
+        let histogram = detector.category_histogram(&[
+            "Googlebot/2.1",
+            "bingbot/2.0",
+            "Scrapy/2.11",
+            "curl/7.68.0",
+            "curl/8.0.1",
+            "Mozilla/5.0",
+        ]);
+        assert_eq!(
+            histogram,
+            HashMap::from([
+                ("crawler".to_string(), 2),
+                ("scraper".to_string(), 1),
+                (UNCATEGORIZED.to_string(), 2),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_case_sensitive_entries() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            file.path(),
+            r#"[
+                "googlebot",
+                { "pattern": "ExactToken", "name": "Exact", "case_sensitive": true },
+                "curl"
+            ]"#,
+        )
+        .unwrap();
+        let detector = BotDetector::from_json_path(file.path().to_str().unwrap()).unwrap();
+
+        assert!(detector.entries()[1].case_sensitive);
+        assert!(detector.is_bot("GOOGLEBOT/2.1"));
+        assert!(detector.is_bot("Mozilla/5.0 ExactToken/1.0"));
+        assert!(!detector.is_bot("Mozilla/5.0 exacttoken/1.0"));
+        assert!(detector.is_bot("CURL/7.68.0"));
+
+        let info = detector.classify("Mozilla/5.0 ExactToken/1.0").unwrap();
+        assert_eq!(info.name, Some("Exact".to_string()));
+        assert_eq!(detector.classify("Curl/7.68.0").unwrap().pattern, "curl");
+        assert_eq!(detector.match_count("exacttoken curl"), 1);
+
+        // the flag round-trips through the object format
+        let json = detector.to_json().unwrap();
+        assert!(json.contains(r#""case_sensitive": true"#));
+    }
+
+    #[test]
+    fn test_strip_quotes() {
+        let detector = BotDetector::from_patterns(&patterns(&["^Googlebot/2\\.1$"])).unwrap();
+        assert!(!detector.is_bot("\"Googlebot/2.1\""));
+        assert!(detector.is_bot_trimmed("\"Googlebot/2.1\""));
+        assert!(detector.is_bot_trimmed("'Googlebot/2.1'"));
+        assert!(detector.is_bot_trimmed("Googlebot/2.1"));
+        assert!(!detector.is_bot_trimmed("\"Googlebot/2.1'"));
+        assert!(!detector.is_bot_trimmed("\"\"Googlebot/2.1\"\""));
+
+        let detector = BotDetector::builder()
+            .patterns(&patterns(&["^Googlebot", "curl\""]))
+            .strip_quotes(true)
+            .build()
+            .unwrap();
+        assert!(detector.is_bot("\"Googlebot/2.1\""));
+        assert_eq!(
+            detector.is_bot_match("\"Googlebot/2.1\""),
+            Some("Googlebot".to_string())
+        );
+        // internal quotes are kept
+        assert!(detector.is_bot("\"x curl\" y\""));
+        assert!(!detector.is_bot("\"curl\""));
+    }
+
+    #[test]
+    fn test_classify_merged() {
+        let detector = BotDetector::from_entries(&[
+            PatternEntry::from("(curl)/(\\d+)?".to_string()),
+            PatternEntry {
+                pattern: "(?:google|bing)bot".to_string(),
+                name: Some("Search".to_string()),
+                ..PatternEntry::default()
+            },
+            PatternEntry::from("(w)(g)(e)t".to_string()),
+            PatternEntry {
+                pattern: "ExactToken".to_string(),
+                case_sensitive: true,
+                ..PatternEntry::default()
+            },
+            PatternEntry::from("bot".to_string()),
+        ])
+        .unwrap();
+
+        for ua in [
+            "curl/7.68.0",
+            "curl",
+            "Mozilla/5.0 (compatible; Bingbot/2.0)",
+            "Wget/1.21",
+            "ExactToken/1.0",
+            "exacttoken/1.0 somebot",
+            "Mozilla/5.0",
+            "",
+        ] {
+            assert_eq!(detector.classify_merged(ua), detector.classify(ua), "{ua}");
+        }
+        assert_eq!(
+            detector.classify_merged("Wget/1.21").unwrap().pattern,
+            "(w)(g)(e)t"
+        );
+
+        let mut detector = BotDetector::from_regex(Regex::new("Googlebot").unwrap());
+        assert_eq!(
+            detector.classify_merged("Googlebot/2.1").unwrap().pattern,
+            "Googlebot"
+        );
+        detector.add_patterns(&patterns(&["(curl)"])).unwrap();
+        assert_eq!(
+            detector.classify_merged("Curl/7.68.0").unwrap().pattern,
+            "(curl)"
+        );
+    }
+
+    #[test]
+    fn test_max_match_len() {
+        let long_ua = format!("curl/{}", "x".repeat(1000));
+        let detector = BotDetector::from_patterns(&patterns(&["curl.*"])).unwrap();
+
+        let matched = detector.is_bot_match(&long_ua).unwrap();
+        assert_eq!(
+            matched.len(),
+            DEFAULT_MAX_MATCH_LEN + TRUNCATION_MARKER.len()
+        );
+        assert!(matched.ends_with(TRUNCATION_MARKER));
+        let explanation = detector.explain(&long_ua).unwrap();
+        assert_eq!(explanation.matched_text, matched);
+        assert_eq!(explanation.span, (0, long_ua.len()));
+        assert_eq!(
+            detector.is_bot_match("curl/7.68.0"),
+            Some("curl/7.68.0".to_string())
+        );
+
+        // cut at a character boundary
+        let detector = BotDetector::builder()
+            .patterns(&patterns(&["bot.*"]))
+            .max_match_len(Some(4))
+            .build()
+            .unwrap();
+        assert_eq!(
+            detector.classify("botéé").unwrap().matched_text,
+            format!("bot{TRUNCATION_MARKER}")
+        );
+
+        let detector = BotDetector::builder()
+            .patterns(&patterns(&["curl.*"]))
+            .max_match_len(None)
+            .build()
+            .unwrap();
+        assert_eq!(detector.is_bot_match(&long_ua), Some(long_ua));
+    }
+
+    #[test]
+    fn test_from_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("google.json"), r#"["Googlebot"]"#).unwrap();
+        std::fs::create_dir(dir.path().join("tools")).unwrap();
+        std::fs::write(dir.path().join("tools/curl.json"), r#"["curl"]"#).unwrap();
+        let dir_path = dir.path().to_str().unwrap();
+
+        let detector = BotDetector::from_dir(dir_path).unwrap();
+        assert!(detector.is_bot("Googlebot/2.1"));
+        assert!(!detector.is_bot("curl/7.68.0"));
+
+        let detector = BotDetector::builder()
+            .dir(dir_path)
+            .recursive(true)
+            .build()
+            .unwrap();
+        assert!(detector.is_bot("curl/7.68.0"));
+
+        let empty = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            BotDetector::from_dir(empty.path().to_str().unwrap()),
+            Err(BotDetectorError::Empty)
+        ));
+    }
+
+    #[test]
+    fn test_is_bot_reader() {
+        struct Failing;
+
+        impl Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("connection reset"))
+            }
+        }
+
+        let detector = BotDetector::from_patterns(&patterns(&["Googlebot"])).unwrap();
+        let ua = format!("{}Googlebot/2.1", "x".repeat(100));
+
+        assert!(detector.is_bot_reader(ua.as_bytes(), 1024).unwrap());
+        assert!(detector.is_bot_reader(ua.as_bytes(), 109).unwrap());
+        assert!(!detector.is_bot_reader(ua.as_bytes(), 108).unwrap());
+        assert!(!detector.is_bot_reader(&b""[..], 1024).unwrap());
+        assert!(matches!(
+            detector.is_bot_reader(Failing, 1024),
+            Err(BotDetectorError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_pattern_regexes() {
+        let mut detector = BotDetector::from_patterns(&patterns(&["Googlebot", "curl"])).unwrap();
+        let pattern_regexes = detector.pattern_regexes();
+        assert_eq!(pattern_regexes.len(), 2);
+        assert_eq!(pattern_regexes[0].0, "Googlebot");
+        assert!(pattern_regexes[0].1.is_match(b"googlebot/2.1").unwrap());
+        assert!(!pattern_regexes[1].1.is_match(b"googlebot/2.1").unwrap());
+
+        detector.add_patterns(&patterns(&["wget"])).unwrap();
+        assert_eq!(detector.pattern_regexes()[2].0, "wget");
+
+        let detector = BotDetector::from_regex(Regex::new("Googlebot").unwrap());
+        assert_eq!(detector.pattern_regexes()[0].0, "Googlebot");
+    }
+
+    #[test]
+    fn test_category_match_counts() {
+        let entry = |pattern: &str, category: Option<&str>| PatternEntry {
+            pattern: pattern.to_string(),
+            category: category.map(ToString::to_string),
+            ..PatternEntry::default()
+        };
+        let detector = BotDetector::from_entries(&[
+            entry("scrapy", Some("scraper")),
+            entry("python", Some("scraper")),
+            entry("bot", Some("crawler")),
+            entry("http", None),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            detector.category_match_counts("Scrapy/2.11 python-requests +http://example.com/bot"),
+            HashMap::from([
+                ("scraper".to_string(), 2),
+                ("crawler".to_string(), 1),
+                (UNCATEGORIZED.to_string(), 1),
+            ])
+        );
+        assert!(detector.category_match_counts("Mozilla/5.0").is_empty());
+        assert!(detector.category_match_counts("").is_empty());
+    }
+
+    #[test]
+    fn test_assert_detects() {
+        let builder = BotDetector::builder()
+            .patterns(&patterns(&["Googlebot", "curl"]))
+            .assert_detects(&["Googlebot/2.1"]);
+
+        assert!(builder
+            .clone()
+            .assert_detects(&["curl/7.68.0"])
+            .build()
+            .is_ok());
+
+        let error = builder
+            .clone()
+            .assert_detects(&["Mozilla/5.0"])
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(error, BotDetectorError::AssertionFailed { ref ua } if ua == "Mozilla/5.0")
+        );
+        assert_eq!(
+            error.error_message(),
+            r#"User agent not detected as a bot: "Mozilla/5.0""#
+        );
+
+        // every failing user agent is reported
+        let error = builder
+            .assert_detects(&["Mozilla/5.0", "wget/1.21"])
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            error.error_message(),
+            r#"2 errors: User agent not detected as a bot: "Mozilla/5.0"; User agent not detected as a bot: "wget/1.21""#
+        );
+    }
+
+    #[test]
+    fn test_build_collects_errors() {
+        let error = BotDetector::builder()
+            .patterns(&patterns(&["Googlebot", "(unclosed", "curl", "[z-a]"]))
+            .exclusions(&patterns(&["(also unclosed"]))
+            .max_patterns(2)
+            .assert_detects(&["Mozilla/5.0"])
+            .build()
+            .unwrap_err();
+        let BotDetectorError::Multiple(errors) = error else {
+            panic!("expected several errors, got {error:?}");
+        };
+        assert_eq!(errors.len(), 4);
+        assert!(matches!(
+            errors[0],
+            BotDetectorError::TooManyPatterns { count: 4, limit: 2 }
+        ));
+        assert!(errors[1..]
+            .iter()
+            .all(|e| matches!(e, BotDetectorError::RegexCompile(_))));
+
+        // with the patterns fixed, the assertions run despite the count
+        let error = BotDetector::builder()
+            .patterns(&patterns(&["Googlebot", "curl", "wget"]))
+            .max_patterns(2)
+            .assert_detects(&["Mozilla/5.0"])
+            .build()
+            .unwrap_err();
+        let BotDetectorError::Multiple(errors) = error else {
+            panic!("expected several errors, got {error:?}");
+        };
+        assert!(matches!(
+            errors[..],
+            [
+                BotDetectorError::TooManyPatterns { .. },
+                BotDetectorError::AssertionFailed { .. }
+            ]
+        ));
+
+        // reading the patterns fails early
+        assert!(matches!(
+            BotDetector::builder()
+                .json_path("missing.json")
+                .max_patterns(0)
+                .build(),
+            Err(BotDetectorError::Io(_))
+        ));
+        assert!(matches!(
+            BotDetector::builder().build(),
+            Err(BotDetectorError::Empty)
+        ));
+    }
+
+    #[test]
+    fn test_ignore_url_segments() {
+        let ua = "Mozilla/5.0 (compatible; Example/1.0; +http://www.example.com/bot.html)";
+        let build = |ignore: bool| {
+            BotDetector::builder()
+                .patterns(&patterns(&["bot", "Googlebot"]))
+                .ignore_url_segments(ignore)
+                .build()
+                .unwrap()
+        };
+        let detector = build(true);
+
+        assert!(build(false).is_bot(ua));
+        assert!(!detector.is_bot(ua));
+        assert!(!detector.is_bot("Example/1.0 HTTPS://example.com/robots.txt"));
+        assert!(!detector.is_bot("Example/1.0 (http://a.example/bot; https://b.example/bot)"));
+        assert!(detector
+            .is_bot("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)"));
+
+        // only complete schemes start a URL
+        assert!(detector.is_bot("httpbot/1.0"));
+        assert_eq!(
+            strip_urls("A/1.0 (+http://x.example/bot) http B"),
+            "A/1.0 () http B"
+        );
+        assert!(matches!(strip_urls("Mozilla/5.0"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_name_of_match() {
+        let detector = BotDetector::from_entries(&[
+            PatternEntry {
+                pattern: "(?:google|bing)bot".to_string(),
+                name: Some("Search".to_string()),
+                ..PatternEntry::default()
+            },
+            PatternEntry::from("curl/\\d+".to_string()),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            detector.name_of_match("Mozilla/5.0 (compatible; bingbot/2.0)"),
+            Some("Search".to_string())
+        );
+        assert_eq!(
+            detector.name_of_match("curl/7.68.0"),
+            Some("curl/7".to_string())
+        );
+        assert_eq!(detector.name_of_match("Mozilla/5.0"), None);
+    }
+
+    #[test]
+    fn test_classify_many() {
+        let detector = BotDetector::from_patterns(&patterns(&["Googlebot", "curl"])).unwrap();
+        let uas = ["Googlebot/2.1", "Mozilla/5.0", "", "curl/7.68.0"];
+
+        let results: Vec<_> = detector.classify_many(uas.iter().copied()).collect();
+        let expected: Vec<_> = uas.iter().map(|ua| detector.classify(ua)).collect();
+        assert_eq!(results, expected);
+
+        // the iterator is lazy, only advanced user agents are classified
+        let lines = "Mozilla/5.0\ncurl/7.68.0\nGooglebot/2.1";
+        let first_bot = detector.classify_many(lines.lines()).flatten().next();
+        assert_eq!(first_bot.unwrap().pattern, "curl");
+    }
+
+    #[test]
+    fn test_good_bots() {
+        let googlebot = "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)";
+        let detector = BotDetector::from_entries(&[
+            PatternEntry {
+                pattern: "bingbot|msnbot".to_string(),
+                name: Some("Bingbot".to_string()),
+                ..PatternEntry::default()
+            },
+            PatternEntry::from("googlebot".to_string()),
+            PatternEntry::from("scrapy".to_string()),
+        ])
+        .unwrap();
+
+        assert!(detector.is_good_bot(googlebot));
+        assert!(detector.is_good_bot("msnbot/2.0b"));
+        assert!(!detector.is_bad_bot(googlebot));
+        assert!(detector.is_bad_bot("Scrapy/2.11"));
+        assert!(!detector.is_good_bot("Scrapy/2.11"));
+        assert!(!detector.is_good_bot("Mozilla/5.0"));
+        assert!(!detector.is_bad_bot("Mozilla/5.0"));
+
+        let detector = BotDetector::builder()
+            .patterns(&patterns(&["googlebot", "scrapy"]))
+            .good_bots(&patterns(&["Scrapy"]))
+            .build()
+            .unwrap();
+        assert!(detector.is_good_bot("Scrapy/2.11"));
+        assert!(detector.is_bad_bot(googlebot));
+    }
+
+    #[test]
+    fn test_detect_id() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            file.path(),
+            r#"[{ "pattern": "googlebot/\\d", "id": "google-1" }, "curl"]"#,
+        )
+        .unwrap();
+        let detector = BotDetector::from_json_path(file.path().to_str().unwrap()).unwrap();
+
+        assert_eq!(
+            detector.detect_id("Googlebot/2.1"),
+            Some("google-1".to_string())
+        );
+        assert_eq!(
+            detector.detect_id("curl/7.68.0"),
+            Some(detector.entries()[1].stable_id())
+        );
+        assert_eq!(detector.detect_id("Mozilla/5.0"), None);
+
+        // the id survives a tweak of the pattern text
+        std::fs::write(
+            file.path(),
+            r#"[{ "pattern": "googlebot/\\d+", "id": "google-1" }]"#,
+        )
+        .unwrap();
+        let detector = BotDetector::from_json_path(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(
+            detector.detect_id("Googlebot/2.1"),
+            Some("google-1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_prepare() {
+        let detector = BotDetector::builder()
+            .entries(&[
+                PatternEntry {
+                    pattern: "Googlebot".to_string(),
+                    weight: 2.0,
+                    ..PatternEntry::default()
+                },
+                PatternEntry::from("compatible".to_string()),
+            ])
+            .precompute_lowercase(true)
+            .strip_quotes(true)
+            .build()
+            .unwrap();
+
+        for ua in [
+            "\"Mozilla/5.0 (compatible; Googlebot/2.1)\"",
+            "Mozilla/5.0 (X11; Linux x86_64)",
+            "\"\"",
+            "",
+        ] {
+            let prepared = detector.prepare(ua);
+            assert_eq!(prepared.is_bot(), detector.is_bot(ua), "{ua}");
+            assert_eq!(prepared.is_bot_match(), detector.is_bot_match(ua), "{ua}");
+            assert_eq!(prepared.classify(), detector.classify(ua), "{ua}");
+            assert_eq!(prepared.explain(), detector.explain(ua), "{ua}");
+            assert_eq!(prepared.match_count(), detector.match_count(ua), "{ua}");
+            assert!((prepared.score() - detector.score(ua)).abs() < f32::EPSILON);
+        }
+    }
+
+    #[test]
+    fn test_jit_stack_retry() {
+        let detector = BotDetector::builder()
+            .patterns(&["(?:(a)|b)*$".to_string()])
+            .jit(true)
+            .build()
+            .unwrap();
+        let ua = "ab".repeat(1000);
+
+        assert!(detector.is_bot(&ua));
+        assert!(detector.is_bot_match(&ua).is_some());
+        assert!(detector.try_is_bot(&ua).unwrap());
+        assert_eq!(detector.match_count(&ua), 1);
+        assert!(detector.has_at_least_matches(&ua, 1));
+        assert!(detector.is_bot_with_min_matches(&ua, 1));
+
+        let matching = detector.matches_with_positions(&ua);
+        let non_matching = detector.non_matching_patterns(&ua);
+        assert_eq!(matching.len() + non_matching.len(), 1);
+        assert!(matching
+            .iter()
+            .all(|(pattern, _)| !non_matching.contains(pattern)));
+
+        // a pattern running out of JIT stack still covers the patterns it matches along with
+        let detector = BotDetector::builder()
+            .patterns(&patterns(&["(?:(a)|b)*$", "b$"]))
+            .jit(true)
+            .build()
+            .unwrap();
+        assert_eq!(
+            detector.redundancy_report(&[&ua]),
+            vec![
+                ("b$".to_string(), "(?:(a)|b)*$".to_string()),
+                ("(?:(a)|b)*$".to_string(), "b$".to_string()),
+            ]
+        );
+
+        let detector = BotDetector::builder()
+            .patterns(&["(?:(a)|b)*$".to_string()])
+            .jit(true)
+            .on_match_error(ErrorPolicy::Propagate)
+            .build()
+            .unwrap();
+        assert!(detector.is_bot(&ua));
+        assert!(matches!(
+            detector.try_is_bot(&ua),
+            Err(BotDetectorError::JitStack)
+        ));
+        assert!(detector.try_is_bot("ab").unwrap());
+
+        assert!(detector.classify(&ua).is_some());
+        assert!(matches!(
+            detector.try_classify(&ua),
+            Err(BotDetectorError::JitStack)
+        ));
+        assert_eq!(
+            detector.try_classify("ab").unwrap(),
+            detector.classify("ab")
+        );
+        assert_eq!(detector.try_classify("").unwrap(), None);
+    }
+
+    #[test]
+    fn test_non_matching_patterns() {
+        let patterns = ["bot", "Google", "curl", "^wget"].map(String::from);
+        let detector = BotDetector::from_patterns(&patterns).unwrap();
+        let ua = "Googlebot/2.1";
+
+        let matching: Vec<String> = detector
+            .matches_with_positions(ua)
+            .into_iter()
+            .map(|(pattern, _)| pattern)
+            .collect();
+        let non_matching = detector.non_matching_patterns(ua);
+        assert_eq!(non_matching, vec!["curl", "^wget"]);
+        assert_eq!(matching.len() + non_matching.len(), patterns.len());
+
+        assert_eq!(detector.non_matching_patterns(""), patterns);
+    }
+
+    #[test]
+    fn test_to_json_round_trip() {
+        let json = r#"[
+            "(?<! cu)bots?(?:\\b|_)",
+            { "pattern": "googlebot", "id": "google", "name": "Googlebot", "category": "crawler", "weight": 2.0 },
+            { "pattern": "ExactToken", "name": "Exact", "case_sensitive": true },
+            { "pattern": "curl", "category": "tool", "weight": 0.5 }
+        ]"#;
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), json).unwrap();
+        let mut detector = BotDetector::from_json_path(file.path().to_str().unwrap()).unwrap();
+
+        let exported: serde_json::Value =
+            serde_json::from_str(&detector.to_json().unwrap()).unwrap();
+        let original: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(exported, original);
+
+        // appending patterns keeps the metadata of the loaded ones
+        detector.add_patterns(&patterns(&["wget"])).unwrap();
+        let mut expected = original;
+        expected.as_array_mut().unwrap().push("wget".into());
+        let exported: serde_json::Value =
+            serde_json::from_str(&detector.to_json().unwrap()).unwrap();
+        assert_eq!(exported, expected);
+    }
+
+    #[test]
+    fn test_sort_tokens() {
+        let detector = BotDetector::builder()
+            .patterns(&patterns(&["compatible evilbot/1\\.0 mozilla/5\\.0"]))
+            .sort_tokens(true)
+            .build()
+            .unwrap();
+
+        assert!(detector.is_bot("Mozilla/5.0 (compatible; EvilBot/1.0)"));
+        assert!(detector.is_bot("EvilBot/1.0 (compatible) Mozilla/5.0"));
+        assert!(detector.is_bot("(Mozilla/5.0,  compatible;EvilBot/1.0)"));
+        assert!(!detector.is_bot("Mozilla/5.0 (compatible; EvilBot/2.0)"));
+        assert_eq!(
+            detector.is_bot_match("Mozilla/5.0 (compatible; EvilBot/1.0)"),
+            Some("compatible EvilBot/1.0 Mozilla/5.0".to_string())
+        );
+
+        let unsorted =
+            BotDetector::from_patterns(&patterns(&["compatible evilbot/1\\.0 mozilla/5\\.0"]))
+                .unwrap();
+        assert!(!unsorted.is_bot("Mozilla/5.0 (compatible; EvilBot/1.0)"));
+    }
+
+    #[test]
+    fn test_with_default_patterns() {
+        let mut detector = BotDetector::with_default_patterns().unwrap();
+        assert_eq!(
+            detector.pattern_version(),
+            Some(crate::BUNDLED_PATTERNS_VERSION)
+        );
+        assert_eq!(
+            detector.patterns(),
+            BotDetector::from_json_path("src/patterns.json")
+                .unwrap()
+                .patterns()
+        );
+        assert_eq!(
+            BotDetector::from_patterns(&patterns(&["curl"]))
+                .unwrap()
+                .pattern_version(),
+            None
+        );
+
+        detector
+            .add_patterns(&patterns(&["internal-probe"]))
+            .unwrap();
+        assert_eq!(detector.pattern_version(), None);
+    }
+
+    #[test]
+    fn test_extract_version() {
+        let detector = BotDetector::from_entries(&[
+            PatternEntry {
+                pattern: "Googlebot/(?<version>[\\d.]+)".to_string(),
+                name: Some("Googlebot".to_string()),
+                ..PatternEntry::default()
+            },
+            PatternEntry::from("bingbot(?:/(?<version>[\\d.]+))?".to_string()),
+            PatternEntry::from("curl".to_string()),
+        ])
+        .unwrap();
+
+        assert!(detector.merged_pattern().unwrap().starts_with("(?J)"));
+        assert_eq!(
+            detector.extract_version("Mozilla/5.0 (compatible; Googlebot/2.1)"),
+            Some(("Googlebot".to_string(), "2.1".to_string()))
+        );
+        assert_eq!(
+            detector.extract_version("Mozilla/5.0 (compatible; bingbot/2.0)"),
+            Some((
+                "bingbot(?:/(?<version>[\\d.]+))?".to_string(),
+                "2.0".to_string()
+            ))
+        );
+        assert_eq!(detector.extract_version("bingbot"), None);
+        assert_eq!(detector.extract_version("curl/7.68.0"), None);
+        assert_eq!(detector.extract_version("Mozilla/5.0"), None);
+        assert!(detector.classify_merged("bingbot/2.0").is_some());
+
+        let plain = BotDetector::from_patterns(&patterns(&["(?<!cu)bot"])).unwrap();
+        assert_eq!(plain.merged_pattern(), Some("(?<!cu)bot"));
+    }
+
+    #[test]
+    fn test_on_match_error() {
+        let build = |policy| {
+            BotDetector::builder()
+                .patterns(&patterns(&["(a+)+$", "curl"]))
+                .on_match_error(policy)
+                .build()
+                .unwrap()
+        };
+        // backtracks past pcre2's match limit
+        let ua = format!("{}b", "a".repeat(30));
+
+        let detector = build(ErrorPolicy::AsNotBot);
+        assert!(!detector.is_bot(&ua));
+        assert_eq!(detector.classify(&ua), None);
+        assert!(!detector.try_is_bot(&ua).unwrap());
+        assert!(detector.is_bot("curl/7.68.0"));
+
+        let detector = build(ErrorPolicy::AsBot);
+        assert!(detector.is_bot(&ua));
+        assert_eq!(detector.classify(&ua).unwrap().pattern, "");
+        assert!(detector.try_is_bot(&ua).unwrap());
+        assert!(!detector.is_bot("Mozilla/5.0"));
+
+        let detector = build(ErrorPolicy::Propagate);
+        assert!(!detector.is_bot(&ua));
+        assert!(matches!(
+            detector.try_is_bot(&ua),
+            Err(BotDetectorError::Match(_))
+        ));
+        assert!(matches!(
+            detector.try_classify(&ua),
+            Err(BotDetectorError::Match(_))
+        ));
+        assert_eq!(
+            detector
+                .try_classify("curl/7.68.0")
+                .unwrap()
+                .unwrap()
+                .pattern,
+            "curl"
+        );
+    }
+
+    #[test]
+    fn test_strip_mozilla_prefix() {
+        let detector = BotDetector::builder()
+            .patterns(&patterns(&["^\\(compatible; \\w+bot", "^curl/"]))
+            .strip_mozilla_prefix(true)
+            .strip_quotes(true)
+            .build()
+            .unwrap();
+        let ua = "Mozilla/5.0 (compatible; Googlebot/2.1)";
+
+        assert!(detector.is_bot(ua));
+        assert!(detector.is_bot("\"mozilla/5.0 (compatible; bingbot/2.0)\""));
+        assert!(detector.is_bot("curl/7.68.0"));
+        assert!(!detector.is_bot("Mozilla/5.0(compatible; Googlebot/2.1)"));
+        assert_eq!(detector.explain(ua).unwrap().span, (0, 22));
+
+        let unstripped =
+            BotDetector::from_patterns(&patterns(&["^\\(compatible; \\w+bot"])).unwrap();
+        assert!(!unstripped.is_bot(ua));
+    }
+
+    #[test]
+    fn test_summarize_log() {
+        let detector = BotDetector::from_entries(&[
+            PatternEntry {
+                pattern: "googlebot".to_string(),
+                name: Some("Googlebot".to_string()),
+                ..PatternEntry::default()
+            },
+            PatternEntry::from("curl".to_string()),
+        ])
+        .unwrap();
+        let mut log = b"Googlebot/2.1\r\ncurl/7.68.0\n\nMozilla/5.0\n".to_vec();
+        log.extend_from_slice(b"\xff\xfe Googlebot\ngooglebot/2.1");
+
+        let summary = detector.summarize_log(log.as_slice());
+        assert_eq!(summary.total_lines, 6);
+        assert_eq!(summary.bot_lines, 3);
+        assert_eq!(
+            summary.by_name,
+            HashMap::from([("Googlebot".to_string(), 2), ("curl".to_string(), 1)])
+        );
+
+        assert_eq!(detector.summarize_log(&b""[..]), LogSummary::default());
+    }
+
+    #[test]
+    fn test_is_bot_strict() {
+        let patterns = vec!["Googlebot".to_string()];
+        let lossy = BotDetector::from_patterns(&patterns).unwrap();
+        assert!(lossy.is_bot_strict(b"Googlebot/\xff").unwrap());
+        assert!(!lossy.is_bot_strict(b"curl/\xff").unwrap());
+
+        let strict = BotDetector::builder()
+            .patterns(&patterns)
+            .require_utf8(true)
+            .build()
+            .unwrap();
+        assert!(strict.is_bot_strict(b"Googlebot/2.1").unwrap());
+        assert!(!strict.is_bot_strict(b"curl/7.68.0").unwrap());
+        assert!(matches!(
+            strict.is_bot_strict(b"Googlebot/\xff"),
+            Err(BotDetectorError::InvalidUtf8(e)) if e.valid_up_to() == 10
+        ));
+    }
+
+    #[test]
+    fn test_is_minimal_client() {
+        let detector = BotDetector::from_patterns(&["Googlebot".to_string()]).unwrap();
+
+        assert!(detector.is_minimal_client("curl/7.68.0"));
+        assert!(detector.is_minimal_client(" python-requests/2.25 "));
+        assert!(detector.is_minimal_client("Go-http-client/1.1"));
+
+        assert!(!detector.is_minimal_client(""));
+        assert!(!detector.is_minimal_client("curl"));
+        assert!(!detector.is_minimal_client("curl/"));
+        assert!(!detector.is_minimal_client("curl/latest"));
+        assert!(!detector.is_minimal_client("my tool/1.0"));
+        assert!(!detector.is_minimal_client("curl/7.68.0 libcurl/7.68.0"));
+        assert!(!detector.is_minimal_client("WebKit/605.1"));
+        assert!(!detector.is_minimal_client(&format!("{}/1.0", "a".repeat(60))));
+        assert!(!detector.is_minimal_client(
+            "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0"
+        ));
+    }
+
+    #[test]
+    fn test_newline() {
+        let patterns = vec!["bot$".to_string(), "^Mozilla".to_string()];
+        let ua = "Mozilla/5.0 bot\r\nExtra";
+        let build = |newline: Option<Newline>| {
+            let builder = BotDetector::builder().patterns(&patterns).multiline(true);
+            match newline {
+                Some(newline) => builder.newline(newline),
+                None => builder,
+            }
+            .build()
+            .unwrap()
+        };
+
+        // `\r` is left before the linefeed ending the line
+        let lf = vec![("^Mozilla".to_string(), 0)];
+        assert_eq!(build(None).matches_with_positions(ua), lf);
+        assert_eq!(build(Some(Newline::Lf)).matches_with_positions(ua), lf);
+
+        for newline in [Newline::Crlf, Newline::Any, Newline::Cr] {
+            let detector = build(Some(newline));
+            assert!(detector.is_bot(ua));
+            assert_eq!(detector.explain("bot\r\nExtra").unwrap().pattern, "bot$");
+        }
+
+        let crlf = BotDetector::builder()
+            .patterns(&patterns)
+            .newline(Newline::Crlf)
+            .build()
+            .unwrap();
+        assert_eq!(crlf.merged_pattern(), Some("(*CRLF)bot$|^Mozilla"));
+        assert!(crlf.is_bot("curl bot\r\n"));
+        assert!(!crlf.is_bot("curl bot\n"));
+    }
+
+    #[test]
+    fn test_matched_names() {
+        let json = r#"[
+            { "pattern": "Google", "name": "Googlebot" },
+            { "pattern": "Googlebot", "name": "Googlebot" },
+            { "pattern": "bot", "name": "Generic" },
+            "compatible"
+        ]"#;
+        let detector = BotDetector::from_json_bytes(json.as_bytes()).unwrap();
+
+        let names = detector.matched_names("Mozilla/5.0 (compatible; Googlebot/2.1)");
+        let expected: HashSet<_> = ["Googlebot", "Generic", "compatible"]
+            .map(String::from)
+            .into();
+        assert_eq!(names, expected);
+
+        assert!(detector.matched_names("Mozilla/5.0").is_empty());
+        assert!(detector.matched_names("").is_empty());
+    }
+
+    #[test]
+    fn test_redact_with() {
+        let detector =
+            BotDetector::from_patterns(&patterns(&["Googlebot", "curl", "^", "\\x{a9}"])).unwrap();
+        let mut tokens = Vec::new();
+        let redacted = detector.redact_with("curl/7.68.0 Googlebot/2.1 curl", |token| {
+            tokens.push(token.to_string());
+            "#".repeat(tokens.len())
+        });
+        assert_eq!(redacted, "#/7.68.0 ##/2.1 ###");
+        assert_eq!(tokens, ["curl", "Googlebot", "curl"]);
+
+        // without `utf`, `\x{a9}` matches the last byte of both characters, widened to them
+        let redacted = detector.redact_with("caf\u{e9} \u{a9}", |token| format!("[{token}]"));
+        assert_eq!(redacted, "caf[\u{e9}] [\u{a9}]");
+
+        assert_eq!(
+            detector.redact_with("Mozilla/5.0", |_| String::new()),
+            "Mozilla/5.0"
+        );
+        assert_eq!(detector.redact_with("", |_| String::new()), "");
+    }
+
+    #[test]
+    fn test_extract_token() {
+        let detector = BotDetector::from_patterns(&patterns(&[
+            "(?<token>\\w+bot)/",
+            "(?<token>curl)/(?<version>[\\d.]+)",
+            "wget",
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            detector.extract_token("Mozilla/5.0 (compatible; Googlebot/2.1)"),
+            Some("Googlebot".to_string())
+        );
+        assert_eq!(
+            detector.extract_token("curl/7.68.0"),
+            Some("curl".to_string())
+        );
+        assert_eq!(
+            detector.extract_version("curl/7.68.0"),
+            Some((
+                "(?<token>curl)/(?<version>[\\d.]+)".to_string(),
+                "7.68.0".to_string()
+            ))
+        );
+        assert_eq!(detector.extract_token("Wget/1.21"), None);
+        assert_eq!(detector.extract_token("Mozilla/5.0"), None);
+        assert_eq!(detector.extract_token(""), None);
+    }
+
+    #[test]
+    fn test_matches_any_token() {
+        let detector =
+            BotDetector::from_patterns(&patterns(&["bot c", "^telemetry=", "curl$"])).unwrap();
+
+        assert!(detector.is_bot("Googlebot compatible"));
+        assert!(!detector.matches_any_token("Googlebot compatible"));
+
+        assert!(detector.matches_any_token("Mozilla/5.0;telemetry=on"));
+        assert!(detector.matches_any_token("Mozilla/5.0\ttelemetry=on"));
+        assert!(!detector.matches_any_token("Mozilla/5.0 app-telemetry=on"));
+
+        assert!(detector.matches_any_token("curl; v=7.68.0"));
+        assert!(!detector.is_bot("curl; v=7.68.0"));
+
+        assert!(!detector.matches_any_token(""));
+        assert!(!detector.matches_any_token(" ;; "));
+    }
+
+    #[test]
+    fn test_clone() {
+        let json = r#"[{ "pattern": "Googlebot", "name": "Googlebot" }, "curl"]"#;
+        let detector = BotDetector::from_json_bytes(json.as_bytes()).unwrap();
+        let mut clone = detector.clone();
+
+        assert!(Arc::ptr_eq(&detector.regex, &clone.regex));
+        assert!(Arc::ptr_eq(&detector.compiled, &clone.compiled));
+        for ua in ["Googlebot/2.1", "curl/7.68.0", "Mozilla/5.0", ""] {
+            assert_eq!(detector.is_bot(ua), clone.is_bot(ua));
+            assert_eq!(detector.classify(ua), clone.classify(ua));
+            assert_eq!(detector.classify_merged(ua), clone.classify_merged(ua));
+        }
+
+        // extending a clone leaves the original alone
+        clone.add_patterns(&patterns(&["wget"])).unwrap();
+        assert!(clone.is_bot("Wget/1.21"));
+        assert!(!detector.is_bot("Wget/1.21"));
+        assert_eq!(detector.classify("curl/7.68.0").unwrap().pattern, "curl");
+        assert_eq!(clone.classify("Wget/1.21").unwrap().pattern, "wget");
+    }
+
+    #[test]
+    fn test_control_bytes() {
+        let detector =
+            BotDetector::from_patterns(&patterns(&["Googlebot", "nul\\x00bot", "^curl$"])).unwrap();
+
+        assert!(detector.is_bot_bytes(b"Mozilla/5.0\0(compatible; Googlebot/2.1)"));
+        assert!(detector.is_bot_bytes(b"\x01\x02Googlebot\x7f"));
+        assert!(detector.is_bot_bytes(b"a nul\0bot"));
+        assert!(!detector.is_bot_bytes(b"a nul bot"));
+        assert!(!detector.is_bot_bytes(b"curl\0"));
+        assert!(!detector.is_bot_bytes(b"\0\0\0"));
+        assert!(!detector.is_bot_bytes(b"\0"));
+        assert!(detector.is_bot_strict(b"Google\0bot/\0Googlebot").unwrap());
+
+        // the matched text runs past the NUL
+        let ua = "X nul\0bot/1.0";
+        assert_eq!(detector.is_bot_match(ua), Some("nul\0bot".to_string()));
+        let explanation = detector.explain(ua).unwrap();
+        assert_eq!(explanation.matched_text, "nul\0bot");
+        assert_eq!(explanation.span, (2, 9));
+        assert_eq!(detector.classify(ua).unwrap().matched_text, "nul\0bot");
+        assert_eq!(detector.redact_with(ua, |_| "#".to_string()), "X #/1.0");
+    }
+
+    #[test]
+    fn test_contains_pattern() {
+        let mut detector = BotDetector::from_patterns(&patterns(&["Googlebot", "  "])).unwrap();
+
+        assert!(detector.contains_pattern("Googlebot"));
+        assert!(!detector.contains_pattern("googlebot"));
+        assert!(!detector.contains_pattern(" Googlebot"));
+        assert!(!detector.contains_pattern("Google"));
+        assert!(!detector.contains_pattern("  "));
+
+        detector.add_patterns(&patterns(&["curl"])).unwrap();
+        assert!(detector.contains_pattern("curl"));
+    }
+
+    #[test]
+    fn test_classify_timed() {
+        let detector = BotDetector::from_patterns(&patterns(&["bot", "Googlebot"])).unwrap();
+
+        for ua in ["Mozilla/5.0 (compatible; Googlebot/2.1)", "Mozilla/5.0"] {
+            let (info, _) = detector.classify_timed(ua);
+            assert_eq!(info, detector.classify(ua));
+        }
+        assert_eq!(detector.classify_timed(""), (None, Duration::ZERO));
+
+        // a pathological user agent takes measurably longer than a plain one
+        let slow = BotDetector::from_patterns(&patterns(&["(a+)+$"])).unwrap();
+        let (info, elapsed) = slow.classify_timed(&format!("{}b", "a".repeat(22)));
+        assert!(info.is_none());
+        assert!(elapsed > slow.classify_timed("ab").1);
+    }
+
+    #[test]
+    fn test_empty_ua_is_bot() {
+        let lenient = BotDetector::from_patterns(&patterns(&["Googlebot"])).unwrap();
+        assert!(!lenient.is_bot(""));
+        assert!(!lenient.try_is_bot("").unwrap());
+        assert!(!lenient.prepare("").is_bot());
+
+        let strict = BotDetector::builder()
+            .patterns(&patterns(&["Googlebot"]))
+            .empty_ua_is_bot(true)
+            .build()
+            .unwrap();
+        assert!(strict.is_bot(""));
+        assert!(strict.is_bot_bytes(b""));
+        assert!(strict.prepare("").is_bot());
+        assert!(strict.is_bot("Googlebot/2.1"));
+        assert!(!strict.is_bot(" "));
+        assert!(!strict.is_bot("Mozilla/5.0"));
+        assert_eq!(strict.classify(""), None);
+
+        let propagate = BotDetector::builder()
+            .patterns(&patterns(&["Googlebot"]))
+            .empty_ua_is_bot(true)
+            .on_match_error(ErrorPolicy::Propagate)
+            .build()
+            .unwrap();
+        assert!(propagate.try_is_bot("").unwrap());
+
+        let headless = BotDetector::builder()
+            .patterns(&patterns(&["Googlebot"]))
+            .headless_patterns(&patterns(&["HeadlessChrome"]))
+            .empty_ua_is_bot(true)
+            .build()
+            .unwrap();
+        assert!(headless.is_bot(""));
+        assert!(!headless.is_headless(""));
+        assert!(!strict.is_headless(""));
+    }
+
+    #[test]
+    fn test_merged_pattern() {
+        let detector = BotDetector::builder()
+            .patterns(&patterns(&["GoogleBot", "curl"]))
+            .precompute_lowercase(true)
+            .build()
+            .unwrap();
+
+        assert_eq!(detector.merged_pattern(), Some("googlebot|curl"));
+
+        let mut injected = BotDetector::from_regex(Regex::new("Googlebot").unwrap());
+
+        assert_eq!(injected.merged_pattern(), None);
+        assert_eq!(
+            injected.classify("Googlebot/2.1").map(|info| info.pattern),
+            Some("Googlebot".to_string())
+        );
+
+        injected.add_patterns(&patterns(&["curl"])).unwrap();
+
+        assert_eq!(injected.merged_pattern(), Some("Googlebot|curl"));
+        assert!(injected.is_bot("curl/7.68.0"));
+    }
+
+    #[test]
+    fn test_explain() {
+        let detector = BotDetector::from_entries(&[
+            PatternEntry::from("bot".to_string()),
+            PatternEntry {
+                pattern: "googlebot".to_string(),
+                name: Some("Googlebot".to_string()),
+                ..PatternEntry::default()
+            },
+        ])
+        .unwrap();
+        let explanation = detector
+            .explain("Mozilla/5.0 (compatible; Googlebot/2.1)")
+            .unwrap();
+
+        assert_eq!(
+            explanation,
+            Explanation {
+                pattern: "googlebot".to_string(),
+                name: Some("Googlebot".to_string()),
+                category: None,
+                matched_text: "Googlebot".to_string(),
+                span: (25, 34),
+            }
+        );
+        assert_eq!(
+            serde_json::to_string(&explanation).unwrap(),
+            r#"{"pattern":"googlebot","name":"Googlebot","matched_text":"Googlebot","span":[25,34]}"#
+        );
+        assert_eq!(detector.explain("Mozilla/5.0"), None);
+    }
+
+    #[test]
+    fn test_inline_flags() {
+        // inline flags override the default caseless flag within their scope
+        let detector =
+            BotDetector::from_patterns(&patterns(&["(?-i:Exact)bot", "google"])).unwrap();
+        assert!(detector.is_bot("Exactbot/1.0"));
+        assert!(detector.is_bot("ExactBOT/1.0"));
+        assert!(!detector.is_bot("exactbot/1.0"));
+        assert!(detector.is_bot("GOOGLE"));
+
+        // a bare flag carries over to the following patterns of the merged regex
+        let detector = BotDetector::from_patterns(&patterns(&["(?-i)Exact", "google"])).unwrap();
+        assert!(!detector.is_bot("GOOGLE"));
+
+        let detector = BotDetectorBuilder::new()
+            .patterns(&patterns(&["(?i:Google)bot", "curl"]))
+            .raw(true)
+            .build()
+            .unwrap();
+        assert!(detector.is_bot("GOOGLEbot/2.1"));
+        assert!(!detector.is_bot("GOOGLEBOT/2.1"));
+        assert!(!detector.is_bot("CURL/7.68.0"));
+        assert_eq!(
+            detector.classify("GOOGLEbot/2.1").unwrap().pattern,
+            "(?i:Google)bot"
+        );
+    }
+
+    #[test]
+    fn test_dotall_multiline() {
+        let user_agent = "Mozilla/5.0\nGooglebot/2.1";
+
+        let detector =
+            BotDetector::from_patterns(&patterns(&["mozilla.+googlebot", "^googlebot"])).unwrap();
+        assert!(!detector.is_bot(user_agent));
+
+        let detector = BotDetectorBuilder::new()
+            .patterns(&patterns(&["mozilla.+googlebot"]))
+            .dotall(true)
+            .build()
+            .unwrap();
+        assert!(detector.is_bot(user_agent));
+
+        let detector = BotDetectorBuilder::new()
+            .patterns(&patterns(&["^googlebot"]))
+            .multiline(true)
+            .build()
+            .unwrap();
+        assert!(detector.is_bot(user_agent));
+        assert!(!detector.is_bot("Mozilla/5.0 Googlebot/2.1"));
+    }
+
+    #[test]
+    fn test_precompute_lowercase() {
+        let detector = BotDetector::builder()
+            .patterns(&patterns(&["GoogleBot", r"^\W"]))
+            .precompute_lowercase(true)
+            .build()
+            .unwrap();
+
+        assert!(detector.is_bot("Mozilla/5.0 (compatible; GOOGLEBOT/2.1)"));
+        assert_eq!(
+            detector.is_bot_match("Mozilla/5.0 (compatible; Googlebot/2.1)"),
+            Some("googlebot".to_string())
+        );
+
+        // escapes are not lowercased, `\W` still means non-word character
+        assert!(detector.is_bot("<script>"));
+        assert!(!detector.is_bot("Mozilla/5.0"));
+
+        // nor are verb names, which pcre2 rejects in lowercase
+        let detector = BotDetector::builder()
+            .patterns(&patterns(&["(*UCP)(*LIMIT_MATCH=1000)GoogleBot(*SKIP)/"]))
+            .precompute_lowercase(true)
+            .build()
+            .unwrap();
+        assert_eq!(
+            detector.merged_pattern(),
+            Some("(*UCP)(*LIMIT_MATCH=1000)googlebot(*SKIP)/")
+        );
+        assert!(detector.is_bot("Mozilla/5.0 (compatible; GOOGLEBOT/2.1)"));
+        assert_eq!(
+            lowercase_pattern("(*MARK:Bot)A(*positive_lookahead:B)", false),
+            "(*MARK:bot)a(*positive_lookahead:b)"
+        );
+    }
+
+    #[test]
+    fn test_ascii_lowercase() {
+        let build = |precompute: bool, ascii: bool| {
+            BotDetector::builder()
+                .patterns(&patterns(&["botkit", "ÉCLAIR"]))
+                .precompute_lowercase(precompute)
+                .ascii_lowercase(ascii)
+                .build()
+                .unwrap()
+        };
+        let caseless = build(false, false);
+        let unicode = build(true, false);
+        let ascii = build(true, true);
+
+        // the Kelvin sign lowercases to an ASCII `k` in Unicode only
+        let kelvin = "Bot\u{212A}it/1.0";
+        assert!(unicode.is_bot(kelvin));
+        assert!(!ascii.is_bot(kelvin));
+        assert!(!caseless.is_bot(kelvin));
+
+        // non-ASCII letters are only folded by the Unicode path
+        assert!(unicode.is_bot("éclair/2.0"));
+        assert!(!ascii.is_bot("éclair/2.0"));
+        assert!(!caseless.is_bot("éclair/2.0"));
+        assert!(ascii.is_bot("Éclair/2.0"));
+        assert!(caseless.is_bot("Éclair/2.0"));
+
+        // ASCII user agents behave the same on every path
+        for ua in ["BOTKIT/1.0", "Mozilla/5.0"] {
+            assert_eq!(unicode.is_bot(ua), caseless.is_bot(ua));
+            assert_eq!(ascii.is_bot(ua), caseless.is_bot(ua));
+        }
+
+        // `İ` lowercases to two characters, shifting the spans of the Unicode path
+        let ua = "İİ botkit";
+        assert_eq!(ascii.explain(ua).unwrap().span, (5, 11));
+        assert_eq!(unicode.explain(ua).unwrap().span, (7, 13));
+    }
+}
@@ -4,7 +4,7 @@
 //! handling and propagation.
 
 use pcre2::Error as Pcre2Error;
-use std::{fmt, io};
+use std::{env, fmt, io, str};
 
 /// Custom error type for the bot detector
 #[derive(Debug)]
@@ -17,6 +17,74 @@ pub enum BotDetectorError {
 
     /// Error related to compiling the regex pattern.
     RegexCompile(Pcre2Error),
+
+    /// Error raised when a detector is built from an empty pattern list.
+    Empty,
+
+    /// Error raised when more patterns are loaded than the configured maximum.
+    TooManyPatterns {
+        /// The number of patterns.
+        count: usize,
+        /// The maximum number of patterns.
+        limit: usize,
+    },
+
+    /// Error raised when the global detector is used before it was initialized.
+    NotInitialized,
+
+    /// Error raised by `init_pattern` once the global pattern failed to initialize, with the
+    /// message of the original error.
+    InitFailed(String),
+
+    /// Error related to reading an environment variable, with the name of that variable.
+    EnvVar {
+        /// The name of the variable.
+        name: String,
+        /// The error raised while reading the variable.
+        error: env::VarError,
+    },
+
+    /// Error related to one of several pattern files, with the path of that file.
+    InFile {
+        /// The path of the file.
+        path: String,
+        /// The error raised while loading the file.
+        error: Box<BotDetectorError>,
+    },
+
+    /// Error raised when matching with pcre2's JIT runs out of JIT stack, e.g. with deeply
+    /// nested patterns on a long user agent.
+    JitStack,
+
+    /// Error raised by pcre2 while matching a user agent, other than running out of JIT stack.
+    Match(Pcre2Error),
+
+    /// Error raised by `BotDetector::is_bot_strict` when a user agent is not valid UTF-8 and
+    /// `BotDetectorBuilder::require_utf8` is set.
+    InvalidUtf8(str::Utf8Error),
+
+    /// Error raised when a patterns file declares a schema version this version of the crate
+    /// doesn't know.
+    UnsupportedSchema {
+        /// The schema version declared by the file.
+        schema: u32,
+        /// The latest schema version supported.
+        supported: u32,
+    },
+
+    /// Error raised when a user agent passed to `BotDetectorBuilder::assert_detects` is not
+    /// detected as a bot.
+    AssertionFailed {
+        /// The user agent that was not detected.
+        ua: String,
+    },
+
+    /// Several errors found at once by `BotDetectorBuilder::build`.
+    Multiple(Vec<BotDetectorError>),
+
+    /// Error raised by the file watcher of `BotDetector::watch`.
+    #[cfg(feature = "watch")]
+    Watch(notify::Error),
 }
 
 impl fmt::Display for BotDetectorError {
@@ -25,6 +93,33 @@ impl fmt::Display for BotDetectorError {
             BotDetectorError::Io(e) => write!(f, "IO error: {e}"),
             BotDetectorError::JsonParse(e) => write!(f, "JSON Parse error: {e}"),
             BotDetectorError::RegexCompile(e) => write!(f, "Regex compilation error: {e}"),
+            BotDetectorError::Empty => write!(f, "Empty pattern list"),
+            BotDetectorError::TooManyPatterns { count, limit } => {
+                write!(f, "Too many patterns: {count} exceeds the limit of {limit}")
+            }
+            BotDetectorError::NotInitialized => write!(f, "Global detector is not initialized"),
+            BotDetectorError::InitFailed(e) => write!(f, "Pattern initialization failed: {e}"),
+            BotDetectorError::EnvVar { name, error } => write!(f, "{name}: {error}"),
+            BotDetectorError::InFile { path, error } => write!(f, "{path}: {error}"),
+            BotDetectorError::JitStack => write!(f, "JIT stack limit reached"),
+            BotDetectorError::Match(e) => write!(f, "Regex match error: {e}"),
+            BotDetectorError::InvalidUtf8(e) => write!(f, "Invalid UTF-8 user agent: {e}"),
+            BotDetectorError::UnsupportedSchema { schema, supported } => write!(
+                f,
+                "Unsupported patterns schema version {schema}, the latest supported is {supported}"
+            ),
+            BotDetectorError::AssertionFailed { ua } => {
+                write!(f, "User agent not detected as a bot: {ua:?}")
+            }
+            BotDetectorError::Multiple(errors) => {
+                write!(f, "{} errors", errors.len())?;
+                for (i, error) in errors.iter().enumerate() {
+                    write!(f, "{} {error}", if i == 0 { ':' } else { ';' })?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "watch")]
+            BotDetectorError::Watch(e) => write!(f, "File watcher error: {e}"),
         }
     }
 }
@@ -35,6 +130,13 @@ impl From<io::Error> for BotDetectorError {
     }
 }
 
+#[cfg(feature = "watch")]
+impl From<notify::Error> for BotDetectorError {
+    fn from(value: notify::Error) -> Self {
+        BotDetectorError::Watch(value)
+    }
+}
+
 impl From<serde_json::Error> for BotDetectorError {
     fn from(value: serde_json::Error) -> Self {
         BotDetectorError::JsonParse(value)
@@ -61,4 +163,14 @@ impl BotDetectorError {
     pub fn error_message(&self) -> String {
         format!("{self}")
     }
+
+    /// Turn the errors collected by a check into its result: `Ok` if there are none, the error
+    /// on its own if there is one, and `BotDetectorError::Multiple` otherwise.
+    pub(crate) fn from_all(mut errors: Vec<BotDetectorError>) -> Result<(), BotDetectorError> {
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.remove(0)),
+            _ => Err(BotDetectorError::Multiple(errors)),
+        }
+    }
 }
@@ -4,22 +4,106 @@
 //! It reads patterns from a JSON File, compiles them into a regex, and checks user agents
 //! against these patterns.
 
+pub mod codegen;
+pub mod detector;
 pub mod errors;
+#[cfg(feature = "metrics")]
+mod metrics;
 pub mod pattern;
+mod prefilter;
+pub mod registry;
+#[cfg(feature = "watch")]
+pub mod watch;
+
+pub use crate::detector::{
+    BotDetector, BotDetectorBuilder, BotInfo, Classification, ErrorPolicy, Explanation, LoadReport,
+    LogSummary, Newline, PatternStats, PreparedUa, Signals, DEFAULT_ENGINE_TOKENS,
+    DEFAULT_MAX_MATCH_LEN, MINIMAL_CLIENT_MAX_LEN, TRUNCATION_MARKER, UNCATEGORIZED,
+};
+pub use crate::registry::DetectorRegistry;
+#[cfg(feature = "watch")]
+pub use crate::watch::{WatchHandle, WatchedDetector};
 
 use crate::errors::BotDetectorError;
-use once_cell::sync::OnceCell;
-use pcre2::bytes::Regex;
-use serde::Deserialize;
+use pcre2::bytes::{Regex, RegexBuilder};
+use std::collections::HashMap;
 use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, Mutex, PoisonError, RwLock};
+use std::time::SystemTime;
+
+/// Name of the environment variable holding the pattern file path used by [`is_bot_env`].
+pub const PATTERNS_ENV_VAR: &str = "BOTAGENT_PATTERNS";
+
+/// The version of this crate.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The version of the bundled pattern list, `src/patterns.json`, used by
+/// [`BotDetector::with_default_patterns`].
+///
+/// Bumped to the date of the change whenever the bundled list changes, so deployments can log
+/// which list they run and tell when an update brings new patterns.
+pub const BUNDLED_PATTERNS_VERSION: &str = "2026.10.14";
+
+/// Returns the version of the pcre2 library the crate is built against, e.g. `10.42`.
+///
+/// Regex behavior can differ between pcre2 releases, so this is worth including in bug reports
+/// along with [`VERSION`].
+///
+/// # Example
+///
+/// ```rust
+/// println!("botagent {} (pcre2 {})", botagent::VERSION, botagent::pcre2_version());
+/// ```
+#[must_use]
+pub fn pcre2_version() -> String {
+    let (major, minor) = pcre2::version();
+    format!("{major}.{minor}")
+}
+
+// the outcome of an `init_pattern`, failures included, with the error as a message
+type InitOutcome = Result<Regex, String>;
+
+// the outcome of the first `init_pattern`, leaked so it can be handed out as `'static`; a lock
+// rather than a `OnceCell`, so tests can reset it
+static REGEX: RwLock<Option<&'static InitOutcome>> = RwLock::new(None);
+
+// a lock rather than a `OnceCell`, so tests can reset it
+static GLOBAL_DETECTOR: RwLock<Option<BotDetector>> = RwLock::new(None);
 
-static REGEX: OnceCell<Regex> = OnceCell::new();
+// compiled detectors of the free functions, with the mtime of the file they were built from
+type DetectorCache = HashMap<PathBuf, (SystemTime, Arc<BotDetector>)>;
 
-#[derive(Debug, Deserialize)]
-struct List(Vec<String>);
+static DETECTOR_CACHE: LazyLock<Mutex<DetectorCache>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Returns the compiled detector for `json_path`, building it on first use or when the file's
+/// modification time changed since it was cached.
+fn cached_detector(json_path: &str) -> Result<Arc<BotDetector>, BotDetectorError> {
+    let modified = fs::metadata(json_path)?.modified()?;
+    let mut cache = DETECTOR_CACHE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    if let Some((cached_at, detector)) = cache.get(&PathBuf::from(json_path)) {
+        if *cached_at == modified {
+            return Ok(Arc::clone(detector));
+        }
+    }
+
+    let detector = Arc::new(BotDetector::from_json_path(json_path)?);
+    cache.insert(PathBuf::from(json_path), (modified, Arc::clone(&detector)));
+
+    Ok(detector)
+}
 
 /// Initialize the global regex pattern, only done once.
 ///
+/// The first call compiles the patterns and its outcome, success or failure, is kept for the
+/// lifetime of the process: later calls return the same regex or the same error without
+/// reading `json_path` again, even when concurrent. A failed initialization is therefore sticky
+/// until the process restarts; use [`BotDetector`] directly if loading must be retried.
+///
 /// # Arguments
 ///
 /// * `json_path` - Path to the JSON file containing patterns.
@@ -30,12 +114,8 @@ struct List(Vec<String>);
 ///
 /// # Errors
 ///
-/// This function will return an error if the JSON file cannot be read, parsed, or if the regex
-/// pattern cannot be compiled.
-///
-/// # Panics
-///
-/// Will panic if `generate_pattern` process failed.
+/// Returns `BotDetectorError::InitFailed` with the message of the original error if the JSON
+/// file cannot be read, parsed, or if the regex pattern cannot be compiled.
 ///
 /// # Example
 ///
@@ -44,14 +124,98 @@ struct List(Vec<String>);
 /// let regex = init_pattern("patterns.json").unwrap();
 /// ```
 pub fn init_pattern(json_path: &str) -> Result<&'static Regex, BotDetectorError> {
-    Ok(
-        REGEX.get_or_init(|| match pattern::generate_pattern(json_path) {
-            Ok(regex) => regex,
-            Err(e) => {
-                panic!("Error detected: {:?}", e.error_message());
-            }
-        }),
-    )
+    init_once(&REGEX, json_path)
+}
+
+/// Initialize `slot` from `json_path` on first use, see [`init_pattern`].
+fn init_once(
+    slot: &RwLock<Option<&'static InitOutcome>>,
+    json_path: &str,
+) -> Result<&'static Regex, BotDetectorError> {
+    let initialized = *slot.read().unwrap_or_else(PoisonError::into_inner);
+
+    let outcome = match initialized {
+        Some(outcome) => outcome,
+        None => *slot
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_or_insert_with(|| {
+                let outcome = pattern::generate_pattern(json_path).map_err(|e| e.error_message());
+                Box::leak(Box::new(outcome))
+            }),
+    };
+
+    outcome
+        .as_ref()
+        .map_err(|message| BotDetectorError::InitFailed(message.clone()))
+}
+
+/// Initialize the global detector used by [`global_is_bot`].
+///
+/// The first successful call wins: once the global detector is set, subsequent calls return
+/// `Ok(())` without reading `json_path`. A failed call leaves the global detector
+/// uninitialized, so it can be retried.
+///
+/// # Arguments
+///
+/// * `json_path` - Path to the JSON file containing patterns.
+///
+/// # Errors
+///
+/// Returns a `BotDetectorError` if the JSON file cannot be read or parsed, or if the patterns
+/// cannot be compiled.
+///
+/// # Example
+///
+/// ```no_run
+/// # use botagent::{global_is_bot, init_global};
+/// init_global("patterns.json").unwrap();
+/// assert!(global_is_bot("Googlebot/2.1").unwrap());
+/// ```
+pub fn init_global(json_path: &str) -> Result<(), BotDetectorError> {
+    let mut global = GLOBAL_DETECTOR
+        .write()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    if global.is_none() {
+        *global = Some(BotDetector::from_json_path(json_path)?);
+    }
+
+    Ok(())
+}
+
+/// Check if the given user agent matches the global detector set up by [`init_global`].
+///
+/// # Arguments
+///
+/// * `user_agent` - The user agent string to be checked.
+///
+/// # Errors
+///
+/// Returns `BotDetectorError::NotInitialized` if [`init_global`] has not succeeded yet.
+pub fn global_is_bot(user_agent: &str) -> Result<bool, BotDetectorError> {
+    let global = GLOBAL_DETECTOR
+        .read()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    global
+        .as_ref()
+        .map(|detector| detector.is_bot(user_agent))
+        .ok_or(BotDetectorError::NotInitialized)
+}
+
+/// Clear the global pattern of [`init_pattern`] and the global detector of [`init_global`], so
+/// tests can initialize them again, e.g. with another patterns file.
+///
+/// Only available with the `test-util` feature. References returned by earlier
+/// [`init_pattern`] calls stay valid: the previous regex is leaked rather than dropped, which
+/// is fine for tests but shouldn't be done in a loop.
+#[cfg(any(test, feature = "test-util"))]
+pub fn reset_global() {
+    *REGEX.write().unwrap_or_else(PoisonError::into_inner) = None;
+    *GLOBAL_DETECTOR
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = None;
 }
 
 /// Check if the given user agent includes a bot pattern.
@@ -65,6 +229,13 @@ pub fn init_pattern(json_path: &str) -> Result<&'static Regex, BotDetectorError>
 ///
 /// Returns `true` if the user agent matches any bot pattern, otherwise `false`.
 ///
+/// # Caching
+///
+/// The patterns are compiled on the first call for a given `json_path` and the detector is
+/// reused by later calls with the same path, both here and in [`is_bot_match`]. Each call checks
+/// the file's modification time and recompiles when it changed. Cached detectors are kept for
+/// the lifetime of the process, one per distinct path.
+///
 /// # Errors
 ///
 /// Returns a `BotDetectorError` if there's an issue with reading the patterns or compiling the regex.
@@ -77,8 +248,42 @@ pub fn init_pattern(json_path: &str) -> Result<&'static Regex, BotDetectorError>
 /// assert!(is_bot);
 /// ```
 pub fn is_bot(user_agent: &str, json_path: &str) -> Result<bool, BotDetectorError> {
-    let regex = init_pattern(json_path)?;
-    Ok(regex.is_match(user_agent.as_bytes()).unwrap_or(false))
+    Ok(cached_detector(json_path)?.is_bot(user_agent))
+}
+
+/// Check if the given user agent includes a bot pattern from the file named by the
+/// `BOTAGENT_PATTERNS` environment variable.
+///
+/// # Arguments
+///
+/// * `user_agent` - The user agent string to be checked.
+///
+/// # Returns
+///
+/// Returns `true` if the user agent matches any bot pattern, otherwise `false`.
+///
+/// The variable is read on every call and the compiled patterns are cached per path, see
+/// [`is_bot`].
+///
+/// # Errors
+///
+/// Returns `BotDetectorError::EnvVar` if `BOTAGENT_PATTERNS` is unset or not valid unicode, or
+/// any error [`is_bot`] returns for the path it holds.
+///
+/// # Example
+///
+/// ```no_run
+/// # use botagent::is_bot_env;
+/// // BOTAGENT_PATTERNS=patterns.json
+/// assert!(is_bot_env("Googlebot/2.1").unwrap());
+/// ```
+pub fn is_bot_env(user_agent: &str) -> Result<bool, BotDetectorError> {
+    let json_path = std::env::var(PATTERNS_ENV_VAR).map_err(|error| BotDetectorError::EnvVar {
+        name: PATTERNS_ENV_VAR.to_string(),
+        error,
+    })?;
+
+    is_bot(user_agent, &json_path)
 }
 
 /// Find the first non-empty capture group match of a bot pattern in the user agent string.
@@ -92,6 +297,8 @@ pub fn is_bot(user_agent: &str, json_path: &str) -> Result<bool, BotDetectorErro
 ///
 /// Returns `Some(String)` with the first matched capture group or `None` if no match is found.
 ///
+/// The compiled patterns are cached per path, see [`is_bot`].
+///
 /// # Errors
 ///
 /// Returns a `BotDetectorError` if there's an issue with reading the patterns or compiling the regex.
@@ -104,17 +311,41 @@ pub fn is_bot(user_agent: &str, json_path: &str) -> Result<bool, BotDetectorErro
 /// assert_eq!(matched_pattern, Some("Googlebot".to_string()));
 /// ```
 pub fn is_bot_match(user_agent: &str, json_path: &str) -> Result<Option<String>, BotDetectorError> {
-    let regex = init_pattern(json_path)?;
-
-    if let Ok(Some(caps)) = regex.captures(user_agent.as_bytes()) {
-        if let Some(matched) = caps.get(0) {
-            return Ok(Some(
-                String::from_utf8_lossy(matched.as_bytes()).to_string(),
-            ));
-        }
-    }
+    Ok(cached_detector(json_path)?.is_bot_match(user_agent))
+}
 
-    Ok(None)
+/// Classify the given user agent against the bot patterns of a JSON file.
+///
+/// This is [`BotDetector::classify`] on the detector cached for `json_path`, answering both
+/// whether the user agent is a bot and which pattern matched in a single call.
+///
+/// # Arguments
+///
+/// * `user_agent` - The user agent string to be checked.
+/// * `json_path` - Path to the JSON file containing bot patterns.
+///
+/// # Returns
+///
+/// Returns `Some(BotInfo)` if the user agent is a bot, otherwise `None`.
+///
+/// The compiled patterns are cached per path, see [`is_bot`].
+///
+/// # Errors
+///
+/// Returns a `BotDetectorError` if there's an issue with reading the patterns or compiling the regex.
+///
+/// # Example
+///
+/// ```no_run
+/// # use botagent::classify_from_path;
+/// let info = classify_from_path("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)", "patterns.json").unwrap();
+/// assert!(info.is_some());
+/// ```
+pub fn classify_from_path(
+    user_agent: &str,
+    json_path: &str,
+) -> Result<Option<BotInfo>, BotDetectorError> {
+    Ok(cached_detector(json_path)?.classify(user_agent))
 }
 
 /// Check if the given user agent matches any patterns in the provided JSON file.
@@ -140,11 +371,9 @@ pub fn is_bot_match(user_agent: &str, json_path: &str) -> Result<Option<String>,
 /// assert!(matches.contains(&"Googlebot".to_string()));
 /// ```
 pub fn is_bot_matches(user_agent: &str, json_path: &str) -> Result<Vec<String>, BotDetectorError> {
-    let patterns_json = fs::read_to_string(json_path)?;
-    let patterns: List = serde_json::from_str(&patterns_json)?;
+    let patterns = pattern::read_patterns(json_path)?;
 
     let matches = patterns
-        .0
         .iter()
         .filter_map(|pattern| {
             let regex = Regex::new(format!("(?i){pattern}").as_str()).ok()?;
@@ -187,10 +416,9 @@ pub fn is_bot_pattern(
     user_agent: &str,
     json_path: &str,
 ) -> Result<Option<String>, BotDetectorError> {
-    let patterns_json = fs::read_to_string(json_path)?;
-    let patterns: List = serde_json::from_str(&patterns_json)?;
+    let patterns = pattern::read_patterns(json_path)?;
 
-    for pattern in patterns.0 {
+    for pattern in patterns {
         let regex = Regex::new(&pattern)?;
 
         if regex.is_match(user_agent.as_bytes())? {
@@ -225,11 +453,9 @@ pub fn is_bot_pattern(
 /// assert!(matching_patterns.contains(&"Googlebot/2.1".to_string()));
 /// ```
 pub fn is_bot_patterns(user_agent: &str, json_path: &str) -> Result<Vec<String>, BotDetectorError> {
-    let patterns_json = fs::read_to_string(json_path)?;
-    let patterns: List = serde_json::from_str(&patterns_json)?;
+    let patterns = pattern::read_patterns(json_path)?;
 
     let matching_patterns: Vec<String> = patterns
-        .0
         .into_iter()
         .filter_map(|pattern| {
             let regex = Regex::new(&pattern).ok()?;
@@ -270,6 +496,75 @@ pub fn create_is_bot(custom_pattern: Regex) -> impl Fn(&str) -> bool {
     }
 }
 
+/// Creates a closure like [`create_is_bot`], refusing a regex that matches everything.
+///
+/// The regex is probed against an empty string and a random token: matching both means it
+/// would flag every user agent, which is what an empty pattern (or one with an empty branch
+/// such as `Googlebot|`) does.
+///
+/// # Arguments
+///
+/// * `custom_pattern` - A `Regex` object that represents the custom pattern to be used for matching.
+///
+/// # Errors
+///
+/// Returns `BotDetectorError::Empty` if the regex matches everything, or
+/// `BotDetectorError::RegexCompile` if probing it fails.
+///
+/// # Example
+///
+/// ```rust
+/// # use pcre2::bytes::Regex;
+/// # use botagent::try_create_is_bot;
+/// let custom_bot = try_create_is_bot(Regex::new(r"Googlebot").unwrap()).unwrap();
+/// assert!(custom_bot("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)"));
+/// assert!(try_create_is_bot(Regex::new("").unwrap()).is_err());
+/// ```
+pub fn try_create_is_bot(custom_pattern: Regex) -> Result<impl Fn(&str) -> bool, BotDetectorError> {
+    let token = format!(
+        "{:x}",
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos()
+    );
+
+    if custom_pattern.is_match(b"")? && custom_pattern.is_match(token.as_bytes())? {
+        return Err(BotDetectorError::Empty);
+    }
+
+    Ok(create_is_bot(custom_pattern))
+}
+
+/// Creates a closure like [`try_create_is_bot`] from a pattern string, so callers don't have to
+/// build the regex with pcre2 themselves.
+///
+/// The pattern is compiled caseless, like the patterns of a default detector.
+///
+/// # Arguments
+///
+/// * `pattern` - The regex pattern to be used for matching.
+///
+/// # Errors
+///
+/// Returns `BotDetectorError::RegexCompile` if the pattern cannot be compiled, or
+/// `BotDetectorError::Empty` if it matches everything.
+///
+/// # Example
+///
+/// ```rust
+/// # use botagent::create_is_bot_from_str;
+/// let custom_bot = create_is_bot_from_str("googlebot").unwrap();
+/// assert!(custom_bot("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)"));
+/// assert!(!custom_bot(""));
+/// assert!(create_is_bot_from_str("(unclosed").is_err());
+/// ```
+pub fn create_is_bot_from_str(pattern: &str) -> Result<impl Fn(&str) -> bool, BotDetectorError> {
+    let regex = RegexBuilder::new().caseless(true).build(pattern)?;
+
+    try_create_is_bot(regex)
+}
+
 /// Creates a function to check if a user agent matches any bot pattern from a list.
 ///
 /// # Arguments
@@ -308,6 +603,84 @@ pub fn create_is_bot_from_list(list: &[String]) -> impl Fn(&str) -> bool {
     }
 }
 
+/// Classify the user agent with several detectors, e.g. one per category, returning the first
+/// classification.
+///
+/// The detectors are tried in the given order and the first one classifying the user agent as a
+/// bot wins, so earlier detectors take precedence and the following ones are not run. Within a
+/// detector, the match is attributed as by [`BotDetector::classify`].
+///
+/// # Arguments
+///
+/// * `detectors` - The detectors to try, in order of precedence.
+/// * `user_agent` - The user agent string to be checked.
+///
+/// # Returns
+///
+/// Returns the `BotInfo` of the first detector classifying the user agent, or `None` if none
+/// does.
+///
+/// # Example
+///
+/// ```rust
+/// # use botagent::{any_bot, BotDetector};
+/// let crawlers = BotDetector::from_patterns(&["Googlebot".to_string()]).unwrap();
+/// let tools = BotDetector::from_patterns(&["curl".to_string()]).unwrap();
+/// let info = any_bot(&[&crawlers, &tools], "curl/7.68.0").unwrap();
+/// assert_eq!(info.pattern, "curl");
+/// ```
+#[must_use]
+pub fn any_bot(detectors: &[&BotDetector], user_agent: &str) -> Option<BotInfo> {
+    detectors
+        .iter()
+        .find_map(|detector| detector.classify(user_agent))
+}
+
+/// Compare how two detectors classify a sample of user agents, e.g. before upgrading the
+/// signatures of a running service.
+///
+/// Each sample is classified with [`BotDetector::classify_tri`] by both detectors, and kept
+/// when the results differ. A bot matched by a different pattern, or carrying a different
+/// name or category, counts as a difference.
+///
+/// # Arguments
+///
+/// * `old` - The detector currently in use.
+/// * `new` - The detector meant to replace it.
+/// * `samples` - The user agents to be checked.
+///
+/// # Returns
+///
+/// Returns the user agents classified differently, in sample order, each with its
+/// classification by `old` and by `new`.
+///
+/// # Example
+///
+/// ```rust
+/// # use botagent::{compare_detectors, BotDetector, Classification};
+/// let old = BotDetector::from_patterns(&["Googlebot".to_string()]).unwrap();
+/// let new = BotDetector::from_patterns(&["Googlebot".to_string(), "curl".to_string()]).unwrap();
+/// let changes = compare_detectors(&old, &new, &["Googlebot/2.1", "curl/7.68.0"]);
+/// assert_eq!(changes.len(), 1);
+/// assert_eq!(changes[0].0, "curl/7.68.0");
+/// assert_eq!(changes[0].1, Classification::Human);
+/// ```
+#[must_use]
+pub fn compare_detectors(
+    old: &BotDetector,
+    new: &BotDetector,
+    samples: &[&str],
+) -> Vec<(String, Classification, Classification)> {
+    samples
+        .iter()
+        .filter_map(|user_agent| {
+            let before = old.classify_tri(user_agent);
+            let after = new.classify_tri(user_agent);
+            (before != after).then(|| (user_agent.to_string(), before, after))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod features {
     use super::*;
@@ -334,6 +707,16 @@ mod features {
         assert!(!is_bot("", temp_file.path().to_str().unwrap()).unwrap());
     }
 
+    #[test]
+    fn test_versions() {
+        assert_eq!(VERSION, env!("CARGO_PKG_VERSION"));
+
+        let version = pcre2_version();
+        let (major, minor) = version.split_once('.').unwrap();
+        assert_eq!(major, "10");
+        assert!(minor.parse::<u32>().is_ok());
+    }
+
     #[test]
     fn test_is_bot_match() {
         let bot_user_agent =
@@ -436,6 +819,39 @@ mod features {
         assert!(custom_is_bot(bot_user_agent));
     }
 
+    #[test]
+    fn test_try_create_is_bot() {
+        let custom_is_bot = try_create_is_bot(Regex::new(r"bot").unwrap()).unwrap();
+        assert!(custom_is_bot("Googlebot/2.1"));
+
+        // an anchored empty match is not a match-everything regex
+        assert!(try_create_is_bot(Regex::new(r"^$").unwrap()).is_ok());
+
+        for pattern in ["", "bot|", ".*"] {
+            assert!(matches!(
+                try_create_is_bot(Regex::new(pattern).unwrap()),
+                Err(BotDetectorError::Empty)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_create_is_bot_from_str() {
+        let custom_is_bot = create_is_bot_from_str("googlebot").unwrap();
+        assert!(custom_is_bot("Mozilla/5.0 (compatible; Googlebot/2.1)"));
+        assert!(!custom_is_bot("curl/7.68.0"));
+        assert!(!custom_is_bot(""));
+
+        assert!(matches!(
+            create_is_bot_from_str("(unclosed"),
+            Err(BotDetectorError::RegexCompile(_))
+        ));
+        assert!(matches!(
+            create_is_bot_from_str("bot|"),
+            Err(BotDetectorError::Empty)
+        ));
+    }
+
     #[test]
     fn test_create_is_bot_from_list() {
         let chrome_lighthouse_user_agent_strings = [
@@ -467,6 +883,152 @@ mod features {
         assert!(!is_bot2(ua));
     }
 
+    #[test]
+    fn test_is_bot_cache() {
+        let google = create_temp_patterns_file(&["Googlebot"]);
+        let curl = create_temp_patterns_file(&["curl"]);
+
+        // each path gets its own detector
+        assert!(is_bot("Googlebot/2.1", google.path().to_str().unwrap()).unwrap());
+        assert!(is_bot("curl/7.68.0", curl.path().to_str().unwrap()).unwrap());
+        assert!(!is_bot("curl/7.68.0", google.path().to_str().unwrap()).unwrap());
+
+        // a modified file is recompiled
+        fs::write(google.path(), r#"["curl"]"#).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(google.path())
+            .unwrap()
+            .set_modified(SystemTime::now() + std::time::Duration::from_secs(10))
+            .unwrap();
+
+        assert!(is_bot("curl/7.68.0", google.path().to_str().unwrap()).unwrap());
+        assert!(is_bot("curl/7.68.0", "missing.json").is_err());
+    }
+
+    #[test]
+    fn test_classify_from_path() {
+        let temp_file = create_temp_patterns_file(&["curl", "Googlebot"]);
+        let json_path = temp_file.path().to_str().unwrap();
+
+        let info = classify_from_path("Mozilla/5.0 (compatible; Googlebot/2.1)", json_path)
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.pattern, "Googlebot");
+        assert_eq!(info.matched_text, "Googlebot");
+
+        assert_eq!(classify_from_path("Mozilla/5.0", json_path).unwrap(), None);
+        assert!(classify_from_path("curl/7.68.0", "missing.json").is_err());
+    }
+
+    #[test]
+    fn test_init_once() {
+        let temp_file = create_temp_patterns_file(&["Googlebot"]);
+
+        let slot = RwLock::new(None);
+        let regex = init_once(&slot, temp_file.path().to_str().unwrap()).unwrap();
+        assert!(regex.is_match(b"Googlebot/2.1").unwrap());
+        assert!(init_once(&slot, "missing.json").is_ok());
+
+        // a failure is sticky
+        let slot = RwLock::new(None);
+        let error = init_once(&slot, "missing.json").unwrap_err();
+        assert!(matches!(error, BotDetectorError::InitFailed(_)));
+        assert_eq!(
+            init_once(&slot, temp_file.path().to_str().unwrap())
+                .unwrap_err()
+                .error_message(),
+            error.error_message()
+        );
+    }
+
+    #[test]
+    fn test_global_is_bot() {
+        let temp_file = create_temp_patterns_file(&["Googlebot"]);
+        let other_file = create_temp_patterns_file(&["curl"]);
+
+        reset_global();
+        assert!(matches!(
+            global_is_bot("Googlebot/2.1"),
+            Err(BotDetectorError::NotInitialized)
+        ));
+        assert!(init_global("missing.json").is_err());
+
+        init_global(temp_file.path().to_str().unwrap()).unwrap();
+        assert!(global_is_bot("Googlebot/2.1").unwrap());
+
+        // the first successful initialization sticks until reset
+        init_global(other_file.path().to_str().unwrap()).unwrap();
+        assert!(!global_is_bot("curl/7.68.0").unwrap());
+
+        reset_global();
+        init_global(other_file.path().to_str().unwrap()).unwrap();
+        assert!(global_is_bot("curl/7.68.0").unwrap());
+
+        // the global pattern is reset as well
+        let regex = init_pattern(temp_file.path().to_str().unwrap()).unwrap();
+        reset_global();
+        let other_regex = init_pattern(other_file.path().to_str().unwrap()).unwrap();
+        assert!(regex.is_match(b"Googlebot/2.1").unwrap());
+        assert!(other_regex.is_match(b"curl/7.68.0").unwrap());
+        assert!(!other_regex.is_match(b"Googlebot/2.1").unwrap());
+    }
+
+    #[test]
+    fn test_any_bot() {
+        let crawlers = BotDetector::from_patterns(&["bot".to_string()]).unwrap();
+        let google = BotDetector::from_patterns(&["Googlebot".to_string()]).unwrap();
+        let tools = BotDetector::from_patterns(&["curl".to_string()]).unwrap();
+
+        // the first detector classifying the user agent wins
+        assert_eq!(
+            any_bot(&[&crawlers, &google], "Googlebot/2.1")
+                .unwrap()
+                .pattern,
+            "bot"
+        );
+        assert_eq!(
+            any_bot(&[&google, &crawlers], "Googlebot/2.1")
+                .unwrap()
+                .pattern,
+            "Googlebot"
+        );
+        assert_eq!(
+            any_bot(&[&crawlers, &tools], "curl/7.68.0")
+                .unwrap()
+                .pattern,
+            "curl"
+        );
+        assert_eq!(any_bot(&[&crawlers, &tools], "Mozilla/5.0"), None);
+        assert_eq!(any_bot(&[], "curl/7.68.0"), None);
+    }
+
+    #[test]
+    fn test_compare_detectors() {
+        let old = BotDetector::from_patterns(&["bot".to_string(), "wget".to_string()]).unwrap();
+        let new =
+            BotDetector::from_patterns(&["Googlebot".to_string(), "curl".to_string()]).unwrap();
+        let samples = [
+            "Googlebot/2.1",
+            "curl/7.68.0",
+            "Wget/1.21",
+            "Mozilla/5.0",
+            "",
+        ];
+
+        let changes = compare_detectors(&old, &new, &samples);
+        let user_agents: Vec<_> = changes.iter().map(|(ua, _, _)| ua.as_str()).collect();
+        assert_eq!(user_agents, ["Googlebot/2.1", "curl/7.68.0", "Wget/1.21"]);
+
+        // the same verdict through a different pattern is still a change
+        assert!(matches!(&changes[0].1, Classification::Bot(info) if info.pattern == "bot"));
+        assert!(matches!(&changes[0].2, Classification::Bot(info) if info.pattern == "Googlebot"));
+        assert_eq!(changes[1].1, Classification::Human);
+        assert_eq!(changes[2].2, Classification::Human);
+
+        assert!(compare_detectors(&old, &old, &samples).is_empty());
+    }
+
     #[test]
     fn test_invalid_inputs() {
         let temp_file = create_temp_patterns_file(&["Googlebot"]);
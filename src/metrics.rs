@@ -0,0 +1,220 @@
+//! Metrics Module
+//!
+//! This module counts the checks made by a detector built with
+//! [`BotDetectorBuilder::count_hits`] and renders them in the Prometheus text format.
+//!
+//! [`BotDetectorBuilder::count_hits`]: crate::BotDetectorBuilder::count_hits
+
+use crate::detector::BotDetector;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{PoisonError, RwLock};
+
+/// The counters of a detector built with [`BotDetectorBuilder::count_hits`].
+///
+/// [`BotDetectorBuilder::count_hits`]: crate::BotDetectorBuilder::count_hits
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    checks: AtomicU64,
+    bots: AtomicU64,
+    by_category: RwLock<HashMap<String, AtomicU64>>,
+}
+
+impl Counters {
+    /// Create the counters, starting every category at zero so that all of them are rendered.
+    pub(crate) fn new<'a>(categories: impl IntoIterator<Item = &'a str>) -> Self {
+        let by_category = categories
+            .into_iter()
+            .map(|category| (category.to_string(), AtomicU64::new(0)))
+            .collect();
+
+        Self {
+            by_category: RwLock::new(by_category),
+            ..Self::default()
+        }
+    }
+
+    /// Count a check, with the category of the bot it found, if any.
+    pub(crate) fn record(&self, bot: Option<&str>) {
+        self.checks.fetch_add(1, Ordering::Relaxed);
+
+        let Some(category) = bot else {
+            return;
+        };
+        self.bots.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(count) = self
+            .by_category
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(category)
+        {
+            count.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        // a category added with `add_patterns` after the detector was built
+        self.by_category
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(category.to_string())
+            .or_default()
+            .fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl BotDetector {
+    /// Render the detector's counters in the Prometheus text exposition format, to be served
+    /// from a `/metrics` endpoint.
+    ///
+    /// The counters are only kept by a detector built with
+    /// [`BotDetectorBuilder::count_hits`]; for any other detector this returns an empty
+    /// string. The metrics are:
+    ///
+    /// * `botagent_checks_total` - the user agents checked.
+    /// * `botagent_bots_total` - the user agents detected as bots.
+    /// * `botagent_bots_by_category_total{category="..."}` - the bots per category of the
+    ///   matching entry, with bots lacking a category under `"uncategorized"`.
+    ///
+    /// Only available with the `metrics` feature.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::BotDetector;
+    /// let detector = BotDetector::builder()
+    ///     .patterns(&["Googlebot".to_string()])
+    ///     .count_hits(true)
+    ///     .build()
+    ///     .unwrap();
+    /// detector.is_bot("Googlebot/2.1");
+    /// assert!(detector.metrics_text().contains("botagent_bots_total 1\n"));
+    /// ```
+    ///
+    /// [`BotDetectorBuilder::count_hits`]: crate::BotDetectorBuilder::count_hits
+    #[must_use]
+    pub fn metrics_text(&self) -> String {
+        let Some(counters) = self.counters() else {
+            return String::new();
+        };
+
+        let by_category = counters
+            .by_category
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut categories: Vec<_> = by_category.iter().collect();
+        categories.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut text = String::new();
+        let mut metric = |name: &str, help: &str, samples: &[(String, u64)]| {
+            let _ = writeln!(text, "# HELP {name} {help}");
+            let _ = writeln!(text, "# TYPE {name} counter");
+            for (labels, value) in samples {
+                let _ = writeln!(text, "{name}{labels} {value}");
+            }
+        };
+
+        metric(
+            "botagent_checks_total",
+            "User agents checked.",
+            &[(String::new(), counters.checks.load(Ordering::Relaxed))],
+        );
+        metric(
+            "botagent_bots_total",
+            "User agents detected as bots.",
+            &[(String::new(), counters.bots.load(Ordering::Relaxed))],
+        );
+        metric(
+            "botagent_bots_by_category_total",
+            "User agents detected as bots, per category.",
+            &categories
+                .into_iter()
+                .map(|(category, count)| {
+                    (
+                        format!("{{category=\"{}\"}}", escape_label(category)),
+                        count.load(Ordering::Relaxed),
+                    )
+                })
+                .collect::<Vec<_>>(),
+        );
+
+        text
+    }
+}
+
+/// Escape a label value as the Prometheus text format requires.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod features {
+    use crate::pattern::PatternEntry;
+    use crate::{BotDetector, ErrorPolicy};
+
+    #[test]
+    fn test_metrics_text() {
+        let detector = BotDetector::builder()
+            .entries(&[
+                PatternEntry {
+                    pattern: "googlebot".to_string(),
+                    category: Some("crawler".to_string()),
+                    ..PatternEntry::default()
+                },
+                PatternEntry {
+                    pattern: "curl".to_string(),
+                    category: Some("tool \"cli\"".to_string()),
+                    ..PatternEntry::default()
+                },
+                PatternEntry::from("wget".to_string()),
+            ])
+            .assert_detects(&["curl/7.68.0"])
+            .count_hits(true)
+            .build()
+            .unwrap();
+
+        assert!(detector.is_bot("Googlebot/2.1"));
+        assert!(!detector.is_bot("Mozilla/5.0"));
+        assert!(detector.classify("Wget/1.21").is_some());
+        assert!(detector.prepare("Googlebot/2.1").is_bot());
+
+        assert_eq!(
+            detector.metrics_text(),
+            r#"# HELP botagent_checks_total User agents checked.
+# TYPE botagent_checks_total counter
+botagent_checks_total 4
+# HELP botagent_bots_total User agents detected as bots.
+# TYPE botagent_bots_total counter
+botagent_bots_total 3
+# HELP botagent_bots_by_category_total User agents detected as bots, per category.
+# TYPE botagent_bots_by_category_total counter
+botagent_bots_by_category_total{category="crawler"} 2
+botagent_bots_by_category_total{category="tool \"cli\""} 0
+botagent_bots_by_category_total{category="uncategorized"} 1
+"#
+        );
+
+        // with propagated match errors, the checks that succeed are counted the same
+        let propagate = BotDetector::builder()
+            .patterns(&["googlebot".to_string()])
+            .on_match_error(ErrorPolicy::Propagate)
+            .count_hits(true)
+            .build()
+            .unwrap();
+        assert!(propagate.try_is_bot("Googlebot/2.1").unwrap());
+        assert!(!propagate.try_is_bot("Mozilla/5.0").unwrap());
+        assert!(propagate.try_classify("Googlebot/2.1").unwrap().is_some());
+        assert!(propagate
+            .metrics_text()
+            .contains("botagent_checks_total 3\n"));
+        assert!(propagate.metrics_text().contains("botagent_bots_total 2\n"));
+
+        let uncounted = BotDetector::from_patterns(&["curl".to_string()]).unwrap();
+        assert!(uncounted.is_bot("curl/7.68.0"));
+        assert_eq!(uncounted.metrics_text(), "");
+    }
+}
@@ -5,11 +5,210 @@
 
 use crate::errors::BotDetectorError;
 use pcre2::bytes::{Regex as RegexBytes, RegexBuilder};
-use serde::Deserialize;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
+/// The latest schema version of the patterns file understood by the loaders.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Deserialize)]
+struct PatternList(Vec<PatternEntry>);
+
+/// The part of a versioned patterns file telling its schema version.
+#[derive(Debug, Deserialize)]
+struct SchemaHeader {
+    schema: u32,
+}
+
+/// A patterns file in schema version 1.
 #[derive(Debug, Deserialize)]
-struct PatternList(Vec<String>);
+struct PatternFileV1 {
+    patterns: PatternList,
+}
+
+/// A single entry of a patterns file.
+///
+/// An entry is either a bare pattern string or an object carrying metadata about the bot the
+/// pattern detects. Both forms can be mixed in the same file:
+///
+/// ```json
+/// [
+///   "(?<! cu)bots?(?:\\b|_)",
+///   { "pattern": "googlebot", "name": "Googlebot", "category": "crawler", "weight": 2.5 },
+///   { "pattern": "ExactToken", "case_sensitive": true }
+/// ]
+/// ```
+///
+/// Entries serialize back to the same format, as a bare string when they carry no metadata.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(from = "RawEntry", into = "RawEntry")]
+pub struct PatternEntry {
+    /// The regex pattern.
+    pub pattern: String,
+
+    /// A stable identifier of the pattern, kept when its text is tweaked, if any. See
+    /// [`PatternEntry::stable_id`].
+    pub id: Option<String>,
+
+    /// The name of the bot detected by the pattern, if known.
+    pub name: Option<String>,
+
+    /// The kind of bot detected by the pattern, e.g. `crawler` or `scraper`, if known.
+    pub category: Option<String>,
+
+    /// How much a match of the pattern adds to the score of a user agent. Defaults to `1.0`.
+    pub weight: f32,
+
+    /// Whether the pattern is matched case-sensitively while the rest of the detector is
+    /// caseless. Has no effect with `raw`, which is already case-sensitive, nor with
+    /// `precompute_lowercase`, which lowercases the user agent.
+    pub case_sensitive: bool,
+}
+
+impl Default for PatternEntry {
+    fn default() -> Self {
+        PatternEntry {
+            pattern: String::new(),
+            id: None,
+            name: None,
+            category: None,
+            weight: 1.0,
+            case_sensitive: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum RawEntry {
+    Pattern(String),
+    Entry {
+        pattern: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        category: Option<String>,
+        #[serde(default = "default_weight", skip_serializing_if = "is_default_weight")]
+        weight: f32,
+        #[serde(default, skip_serializing_if = "is_false")]
+        case_sensitive: bool,
+    },
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+// serde's `skip_serializing_if` passes the field by reference
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_default_weight(weight: &f32) -> bool {
+    weight.to_bits() == default_weight().to_bits()
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+impl From<RawEntry> for PatternEntry {
+    fn from(value: RawEntry) -> Self {
+        match value {
+            RawEntry::Pattern(pattern) => PatternEntry::from(pattern),
+            RawEntry::Entry {
+                pattern,
+                id,
+                name,
+                category,
+                weight,
+                case_sensitive,
+            } => PatternEntry {
+                pattern,
+                id,
+                name,
+                category,
+                weight,
+                case_sensitive,
+            },
+        }
+    }
+}
+
+impl From<PatternEntry> for RawEntry {
+    fn from(value: PatternEntry) -> Self {
+        if value.id.is_none()
+            && value.name.is_none()
+            && value.category.is_none()
+            && is_default_weight(&value.weight)
+            && !value.case_sensitive
+        {
+            RawEntry::Pattern(value.pattern)
+        } else {
+            RawEntry::Entry {
+                pattern: value.pattern,
+                id: value.id,
+                name: value.name,
+                category: value.category,
+                weight: value.weight,
+                case_sensitive: value.case_sensitive,
+            }
+        }
+    }
+}
+
+impl PatternEntry {
+    /// Returns the entry's `id`, or else a hash of its pattern text.
+    ///
+    /// The fallback is the 64-bit FNV-1a hash of the pattern, as 16 hex digits, which only
+    /// depends on the pattern text: it is the same across runs, platforms and crate versions,
+    /// but changes with the pattern. Give the entry an `id` to keep it across edits.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use botagent::pattern::PatternEntry;
+    /// let entry = PatternEntry {
+    ///     pattern: "googlebot".to_string(),
+    ///     id: Some("google-1".to_string()),
+    ///     ..PatternEntry::default()
+    /// };
+    /// assert_eq!(entry.stable_id(), "google-1");
+    /// assert_eq!(PatternEntry::from("googlebot".to_string()).stable_id().len(), 16);
+    /// ```
+    #[must_use]
+    pub fn stable_id(&self) -> String {
+        match &self.id {
+            Some(id) => id.clone(),
+            None => format!("{:016x}", fnv1a(self.pattern.as_bytes())),
+        }
+    }
+}
+
+/// The 64-bit FNV-1a hash of `bytes`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+impl From<String> for PatternEntry {
+    fn from(pattern: String) -> Self {
+        PatternEntry {
+            pattern,
+            ..PatternEntry::default()
+        }
+    }
+}
 
 /// Read patterns from a JSON file and generates a regex pattern.
 ///
@@ -34,12 +233,1183 @@ struct PatternList(Vec<String>);
 /// ```
 #[allow(clippy::module_name_repetitions)]
 pub fn generate_pattern(json_path: &str) -> Result<RegexBytes, BotDetectorError> {
+    let patterns = read_patterns(json_path)?;
+
+    compile_merged(&patterns)
+}
+
+/// Read the list of patterns from a JSON file without compiling them.
+///
+/// # Arguments
+///
+/// * `json_path` - Path to the JSON file containing patterns.
+///
+/// # Errors
+///
+/// This function will return an error if the JSON file cannot be read or parsed.
+///
+/// # Example
+///
+/// ```no_run
+/// # use botagent::pattern::read_patterns;
+/// let patterns = read_patterns("patterns.json").unwrap();
+/// ```
+pub fn read_patterns(json_path: &str) -> Result<Vec<String>, BotDetectorError> {
+    let entries = read_entries(json_path)?;
+
+    Ok(entries.into_iter().map(|entry| entry.pattern).collect())
+}
+
+/// Read the pattern entries, including their metadata, from a JSON file.
+///
+/// # Arguments
+///
+/// * `json_path` - Path to the JSON file containing patterns.
+///
+/// # Errors
+///
+/// This function will return an error if the JSON file cannot be read or parsed.
+///
+/// # Example
+///
+/// ```no_run
+/// # use botagent::pattern::read_entries;
+/// let entries = read_entries("patterns.json").unwrap();
+/// ```
+pub fn read_entries(json_path: &str) -> Result<Vec<PatternEntry>, BotDetectorError> {
     // read and parse the JSON file
     let patterns_json = fs::read_to_string(json_path)?;
-    let patterns: PatternList = serde_json::from_str(&patterns_json)?;
 
-    let pattern_str = patterns.0.join("|");
+    parse_entries(&patterns_json)
+}
+
+/// Read and concatenate the pattern entries of several JSON files.
+///
+/// Entries keep the order of the files and, within a file, the file order. A pattern appearing
+/// more than once, in the same or in different files, is only kept the first time.
+///
+/// # Arguments
+///
+/// * `json_paths` - Paths to the JSON files containing patterns.
+///
+/// # Errors
+///
+/// Returns `BotDetectorError::InFile`, naming the offending path, if one of the files cannot be
+/// read or parsed.
+///
+/// # Example
+///
+/// ```no_run
+/// # use botagent::pattern::read_entries_from;
+/// let entries = read_entries_from(&["crawlers.json", "scrapers.json"]).unwrap();
+/// ```
+pub fn read_entries_from(json_paths: &[&str]) -> Result<Vec<PatternEntry>, BotDetectorError> {
+    let mut entries = Vec::new();
+
+    for json_path in json_paths {
+        entries.extend(read_entries_in_file(Path::new(json_path))?);
+    }
+
+    Ok(dedup_entries(entries))
+}
+
+/// Read and concatenate the pattern entries of every `.json` file in a directory.
+///
+/// The files are read in path order and merged as with [`read_entries_from`]. Files with
+/// another extension are ignored, as are subdirectories unless `recursive` is set.
+///
+/// # Arguments
+///
+/// * `dir` - Path to the directory containing the JSON files.
+/// * `recursive` - Whether to also read the JSON files of subdirectories, at any depth.
+///
+/// # Errors
+///
+/// Returns `BotDetectorError::InFile`, naming the offending path, if the directory cannot be
+/// listed or one of the files cannot be read or parsed.
+///
+/// # Example
+///
+/// ```no_run
+/// # use botagent::pattern::read_entries_from_dir;
+/// let entries = read_entries_from_dir("signatures", false).unwrap();
+/// ```
+pub fn read_entries_from_dir(
+    dir: &str,
+    recursive: bool,
+) -> Result<Vec<PatternEntry>, BotDetectorError> {
+    let mut json_paths = Vec::new();
+    collect_json_files(Path::new(dir), recursive, &mut json_paths)?;
+    json_paths.sort();
+
+    let mut entries = Vec::new();
+
+    for json_path in &json_paths {
+        entries.extend(read_entries_in_file(json_path)?);
+    }
+
+    Ok(dedup_entries(entries))
+}
+
+/// Read the pattern entries of a file, naming it in the error.
+fn read_entries_in_file(json_path: &Path) -> Result<Vec<PatternEntry>, BotDetectorError> {
+    fs::read_to_string(json_path)
+        .map_err(BotDetectorError::from)
+        .and_then(|patterns_json| parse_entries(&patterns_json))
+        .map_err(|error| BotDetectorError::InFile {
+            path: json_path.display().to_string(),
+            error: Box::new(error),
+        })
+}
+
+/// Add the `.json` files of `dir`, and of its subdirectories if `recursive`, to `json_paths`.
+fn collect_json_files(
+    dir: &Path,
+    recursive: bool,
+    json_paths: &mut Vec<PathBuf>,
+) -> Result<(), BotDetectorError> {
+    let in_dir = |error: io::Error| BotDetectorError::InFile {
+        path: dir.display().to_string(),
+        error: Box::new(error.into()),
+    };
+
+    for dir_entry in fs::read_dir(dir).map_err(in_dir)? {
+        let path = dir_entry.map_err(in_dir)?.path();
+
+        if path.is_dir() {
+            if recursive {
+                collect_json_files(&path, recursive, json_paths)?;
+            }
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            json_paths.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Drop the entries whose pattern already appeared earlier in the list.
+fn dedup_entries(entries: Vec<PatternEntry>) -> Vec<PatternEntry> {
+    let mut seen = HashSet::new();
+
+    entries
+        .into_iter()
+        .filter(|entry| seen.insert(entry.pattern.clone()))
+        .collect()
+}
+
+/// The patterns added and removed between two versions of a patterns file, as returned by
+/// [`diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PatternDiff {
+    /// The patterns only found in the new file, in the new file's order.
+    pub added: Vec<String>,
+
+    /// The patterns only found in the old file, in the old file's order.
+    pub removed: Vec<String>,
+}
+
+/// Compare two patterns files.
+///
+/// Patterns are compared as exact strings, so a pattern that was only rewritten shows up as
+/// both removed and added. Names and other entry metadata are ignored, and patterns repeated
+/// within a file are only reported once.
+///
+/// # Arguments
+///
+/// * `old_path` - Path to the JSON file currently in use.
+/// * `new_path` - Path to the updated JSON file.
+///
+/// # Errors
+///
+/// Returns `BotDetectorError::InFile`, naming the offending path, if one of the files cannot be
+/// read or parsed.
+///
+/// # Example
+///
+/// ```no_run
+/// # use botagent::pattern::diff;
+/// let diff = diff("patterns.json", "patterns.new.json").unwrap();
+/// for pattern in &diff.added {
+///     println!("+ {pattern}");
+/// }
+/// ```
+pub fn diff(old_path: &str, new_path: &str) -> Result<PatternDiff, BotDetectorError> {
+    let old = read_entries_from(&[old_path])?;
+    let new = read_entries_from(&[new_path])?;
+
+    let old_patterns: HashSet<&str> = old.iter().map(|entry| entry.pattern.as_str()).collect();
+    let new_patterns: HashSet<&str> = new.iter().map(|entry| entry.pattern.as_str()).collect();
+
+    let only_in = |entries: &[PatternEntry], other: &HashSet<&str>| {
+        entries
+            .iter()
+            .filter(|entry| !other.contains(entry.pattern.as_str()))
+            .map(|entry| entry.pattern.clone())
+            .collect()
+    };
+
+    Ok(PatternDiff {
+        added: only_in(&new, &old_patterns),
+        removed: only_in(&old, &new_patterns),
+    })
+}
+
+/// Read the pattern entries from a JSON file that may contain comments (JSONC).
+///
+/// Both `// line` and `/* block */` comments are stripped before parsing. Comment markers
+/// inside string literals are left alone, so a pattern such as `"http://"` is kept intact.
+///
+/// # Arguments
+///
+/// * `json_path` - Path to the JSONC file containing patterns.
+///
+/// # Errors
+///
+/// This function will return an error if the file cannot be read or parsed.
+///
+/// # Example
+///
+/// ```no_run
+/// # use botagent::pattern::read_entries_jsonc;
+/// let entries = read_entries_jsonc("patterns.jsonc").unwrap();
+/// ```
+pub fn read_entries_jsonc(json_path: &str) -> Result<Vec<PatternEntry>, BotDetectorError> {
+    let patterns_jsonc = fs::read_to_string(json_path)?;
+
+    parse_entries(&strip_comments(&patterns_jsonc))
+}
+
+/// Read the patterns from a plain text file holding one pattern per line.
+///
+/// Blank lines and lines starting with `#`, after any indentation, are ignored; a pattern
+/// starting with `#` can be written as `\#`. Lines are kept as is otherwise, apart from their
+/// line ending, `\n` or `\r\n`, and a leading byte order mark.
+///
+/// # Arguments
+///
+/// * `path` - Path to the text file containing patterns.
+///
+/// # Errors
+///
+/// This function will return an error if the file cannot be read or is not valid UTF-8.
+///
+/// # Example
+///
+/// ```no_run
+/// # use botagent::pattern::read_patterns_lines;
+/// let patterns = read_patterns_lines("patterns.txt").unwrap();
+/// ```
+pub fn read_patterns_lines(path: &str) -> Result<Vec<String>, BotDetectorError> {
+    let text = fs::read_to_string(path)?;
+    let text = text.strip_prefix('\u{feff}').unwrap_or(&text);
+
+    Ok(text
+        .lines()
+        .filter(|line| {
+            let line = line.trim_start();
+            !line.is_empty() && !line.starts_with('#')
+        })
+        .map(ToString::to_string)
+        .collect())
+}
+
+/// Strip `//` and `/* */` comments outside of string literals.
+///
+/// Comments are replaced by spaces, and newlines are kept, so positions reported by
+/// `serde_json` still point at the right line.
+fn strip_comments(jsonc: &str) -> String {
+    let mut stripped = String::with_capacity(jsonc.len());
+    let mut chars = jsonc.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            stripped.push(c);
+
+            match c {
+                '\\' => stripped.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '/' && chars.peek() == Some(&'/') {
+            for c in chars.by_ref() {
+                if c == '\n' {
+                    stripped.push(c);
+                    break;
+                }
+            }
+        } else if c == '/' && chars.peek() == Some(&'*') {
+            chars.next();
+            let mut previous = ' ';
+
+            for c in chars.by_ref() {
+                if previous == '*' && c == '/' {
+                    break;
+                }
+
+                stripped.push(if c == '\n' { c } else { ' ' });
+                previous = c;
+            }
+        } else {
+            in_string = c == '"';
+            stripped.push(c);
+        }
+    }
+
+    stripped
+}
+
+/// Parse the pattern entries from the content of a patterns file.
+///
+/// A leading UTF-8 BOM, as saved by some Windows editors, and surrounding whitespace are
+/// stripped before parsing. A file is either a bare array of entries, the legacy format, or an
+/// object telling its `schema` version, such as `{"schema": 1, "patterns": [...]}`; older
+/// layouts are migrated to the latest entries as they are parsed.
+fn parse_entries(patterns_json: &str) -> Result<Vec<PatternEntry>, BotDetectorError> {
+    parse_entries_slice(patterns_json.as_bytes())
+}
+
+/// Parse the pattern entries from the raw bytes of a patterns file, see [`parse_entries`].
+///
+/// Invalid UTF-8 inside the JSON is reported as a `BotDetectorError::JsonParse`.
+pub(crate) fn parse_entries_slice(
+    patterns_json: &[u8],
+) -> Result<Vec<PatternEntry>, BotDetectorError> {
+    let patterns_json = patterns_json
+        .strip_prefix("\u{feff}".as_bytes())
+        .unwrap_or(patterns_json);
+
+    // the legacy format is a bare array, versioned files wrap the entries in an object
+    if patterns_json.trim_ascii_start().first() != Some(&b'{') {
+        let patterns: PatternList = serde_json::from_slice(patterns_json)?;
+        return Ok(patterns.0);
+    }
+
+    let header: SchemaHeader = serde_json::from_slice(patterns_json)?;
+    match header.schema {
+        1 => {
+            let file: PatternFileV1 = serde_json::from_slice(patterns_json)?;
+            Ok(file.patterns.0)
+        }
+        schema => Err(BotDetectorError::UnsupportedSchema {
+            schema,
+            supported: SCHEMA_VERSION,
+        }),
+    }
+}
+
+/// Join the patterns with `|` and compile them into a single caseless regex.
+///
+/// Empty or whitespace-only patterns are left out, since an empty branch would match
+/// everything.
+///
+/// # Arguments
+///
+/// * `patterns` - The patterns to be merged.
+///
+/// # Errors
+///
+/// This function will return an error if the merged pattern cannot be compiled.
+pub fn compile_merged(patterns: &[String]) -> Result<RegexBytes, BotDetectorError> {
+    let pattern_str = patterns
+        .iter()
+        .map(String::as_str)
+        .filter(|pattern| !pattern.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("|");
     let regex = RegexBuilder::new().caseless(true).build(&pattern_str)?;
 
     Ok(regex)
 }
+
+/// Compile each pattern on its own with the given builder.
+///
+/// The results keep the order of `patterns`, so a failure at position `i` belongs to
+/// `patterns[i]`. Unlike the merged regex, per-pattern compilation is independent for each
+/// pattern: with the `parallel` feature enabled the patterns are compiled on the rayon thread
+/// pool.
+///
+/// # Arguments
+///
+/// * `patterns` - The patterns to be compiled.
+/// * `builder` - The pcre2 options used for every pattern.
+///
+/// # Example
+///
+/// ```rust
+/// # use botagent::pattern::compile_all;
+/// # use pcre2::bytes::RegexBuilder;
+/// let results = compile_all(&["googlebot", "(unclosed"], RegexBuilder::new().caseless(true));
+/// assert!(results[0].is_ok());
+/// assert!(results[1].is_err());
+/// ```
+pub fn compile_all<S>(
+    patterns: &[S],
+    builder: &RegexBuilder,
+) -> Vec<Result<RegexBytes, BotDetectorError>>
+where
+    S: AsRef<str> + Sync,
+{
+    let compile = |pattern: &S| Ok(builder.build(pattern.as_ref())?);
+
+    #[cfg(feature = "parallel")]
+    let results = patterns.par_iter().map(compile).collect();
+
+    #[cfg(not(feature = "parallel"))]
+    let results = patterns.iter().map(compile).collect();
+
+    results
+}
+
+/// Measure how long a single pattern takes to match the sample user agents.
+///
+/// The pattern is compiled on its own, caseless like the patterns of a default detector, and
+/// only the matching is timed, once per user agent. A pattern whose time grows much faster
+/// than the others' with longer user agents is likely prone to catastrophic backtracking and
+/// is best fixed before it is merged into the detector's regex. Match errors, e.g. hitting
+/// pcre2's backtracking limit, count towards the time like a failed match.
+///
+/// # Arguments
+///
+/// * `pattern` - The pattern to be measured.
+/// * `uas` - The sample user agents to match the pattern against.
+///
+/// # Errors
+///
+/// Returns a `BotDetectorError` if the pattern cannot be compiled.
+///
+/// # Example
+///
+/// ```rust
+/// # use botagent::pattern::bench_pattern;
+/// let elapsed = bench_pattern("googlebot", &["Googlebot/2.1", "curl/7.68.0"]).unwrap();
+/// println!("googlebot: {elapsed:?}");
+/// ```
+pub fn bench_pattern(pattern: &str, uas: &[&str]) -> Result<Duration, BotDetectorError> {
+    let regex = RegexBuilder::new().caseless(true).build(pattern)?;
+
+    let start = Instant::now();
+    for ua in uas {
+        let _ = std::hint::black_box(regex.is_match(ua.as_bytes()));
+    }
+
+    Ok(start.elapsed())
+}
+
+/// Minimum length of the longest literal run for a pattern not to be considered broad by
+/// [`is_broad`].
+pub const BROAD_MIN_LITERAL_LEN: usize = 3;
+
+/// A problem found in a pattern by [`validate_patterns`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternIssue {
+    /// The position of the pattern in the list.
+    pub index: usize,
+
+    /// The offending pattern.
+    pub pattern: String,
+
+    /// A description of the problem.
+    pub message: String,
+}
+
+/// The result of [`validate_patterns`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// Patterns that cannot be compiled.
+    pub errors: Vec<PatternIssue>,
+
+    /// Patterns that compile but are likely to cause trouble.
+    pub warnings: Vec<PatternIssue>,
+}
+
+impl ValidationReport {
+    /// Returns `true` if no errors nor warnings were found.
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.errors.is_empty() && self.warnings.is_empty()
+    }
+}
+
+/// Check a list of patterns before accepting it.
+///
+/// Each pattern is compiled on its own with the caseless flag; failures are reported as errors.
+/// Patterns flagged by [`is_broad`] or [`redos_risk`] are reported as warnings.
+///
+/// # Arguments
+///
+/// * `patterns` - The patterns to be checked.
+///
+/// # Example
+///
+/// ```rust
+/// # use botagent::pattern::validate_patterns;
+/// let report = validate_patterns(&["googlebot".to_string(), "(unclosed".to_string(), ".*".to_string()]);
+/// assert_eq!(report.errors[0].index, 1);
+/// assert_eq!(report.warnings[0].index, 2);
+/// ```
+#[must_use]
+pub fn validate_patterns(patterns: &[String]) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let results = compile_all(patterns, RegexBuilder::new().caseless(true));
+
+    for (index, (pattern, result)) in patterns.iter().zip(results).enumerate() {
+        let issue = |message: String| PatternIssue {
+            index,
+            pattern: pattern.clone(),
+            message,
+        };
+
+        if let Err(e) = result {
+            report.errors.push(issue(e.error_message()));
+            continue;
+        }
+
+        if is_broad(pattern) {
+            report.warnings.push(issue(
+                "pattern is broad and likely to over-match".to_string(),
+            ));
+        }
+        if let Some(risk) = redos_risk(pattern) {
+            report.warnings.push(issue(risk));
+        }
+    }
+
+    report
+}
+
+/// Heuristically check whether a pattern is dangerously broad, i.e. likely to over-match.
+///
+/// The pattern is split into its top-level `|` branches and is broad if any branch is. A branch
+/// is broad when its longest run of literal characters is shorter than
+/// [`BROAD_MIN_LITERAL_LEN`], unless it is anchored with `^` or `$`. An anchored branch is
+/// still broad if it also contains an unbounded `.*` or `.+`, since e.g. `^.*` matches
+/// anything. Lookaround contents, character classes and escapes such as `\d` do not count as
+/// literals, so patterns relying on those for their specificity may be flagged as well.
+///
+/// # Arguments
+///
+/// * `pattern` - The pattern to be checked.
+///
+/// # Example
+///
+/// ```rust
+/// # use botagent::pattern::is_broad;
+/// assert!(is_broad("a"));
+/// assert!(is_broad(".*"));
+/// assert!(!is_broad("googlebot"));
+/// assert!(!is_broad("^bot"));
+/// ```
+#[must_use]
+pub fn is_broad(pattern: &str) -> bool {
+    split_branches(pattern).into_iter().any(|branch| {
+        let anchored =
+            branch.starts_with('^') || (branch.ends_with('$') && !branch.ends_with("\\$"));
+        let short = longest_literal_run(branch) < BROAD_MIN_LITERAL_LEN;
+
+        short && (!anchored || branch.contains(".*") || branch.contains(".+"))
+    })
+}
+
+/// Statically check whether a pattern risks catastrophic backtracking, without running it.
+///
+/// Two constructs are flagged when a group is repeated with an unbounded quantifier (`*`, `+`
+/// or `{n,}`): a group containing an unbounded quantifier itself, as in `(a+)+`, and a group
+/// whose alternatives may start with the same character, as in `(a|ab)*` or `(\w|\d)+`. The
+/// check is a heuristic: it ignores possessive quantifiers and atomic groups, and treats any
+/// escape such as `\d` and any character class as able to overlap with anything, so it may
+/// flag safe patterns such as `(\d+\.)+`. It doesn't catch every risky pattern either.
+///
+/// # Arguments
+///
+/// * `pattern` - The pattern to be checked.
+///
+/// # Returns
+///
+/// Returns a description of the first risky construct found, or `None` if there is none.
+///
+/// # Example
+///
+/// ```rust
+/// # use botagent::pattern::redos_risk;
+/// assert!(redos_risk("(a+)+$").is_some());
+/// assert!(redos_risk("(?:bot|crawler)+").is_none());
+/// ```
+#[must_use]
+pub fn redos_risk(pattern: &str) -> Option<String> {
+    let chars: Vec<(usize, char)> = pattern.char_indices().collect();
+    let mut top = RedosGroup::new(0);
+    let mut groups: Vec<RedosGroup> = Vec::new();
+    // the group closed by the previous character
+    let mut closed: Option<RedosGroup> = None;
+    let mut i = 0;
+
+    while let Some(&(at, c)) = chars.get(i) {
+        i += 1;
+
+        if let Some(unbounded) = quantifier(&chars, &mut i, c) {
+            if let Some(group) = closed.take().filter(|_| unbounded) {
+                let end = chars.get(i).map_or(pattern.len(), |&(at, _)| at);
+                let source = &pattern[group.start..end];
+                if group.unbounded_inside {
+                    return Some(format!("nested unbounded quantifiers in `{source}`"));
+                }
+                if group.overlapping_branches() {
+                    return Some(format!(
+                        "overlapping alternatives under a quantifier in `{source}`"
+                    ));
+                }
+            }
+            groups.last_mut().unwrap_or(&mut top).unbounded_inside |= unbounded;
+            continue;
+        }
+
+        closed = None;
+        let atom = match c {
+            '\\' => chars.get(i).map(|&(_, e)| {
+                i += 1;
+                if e.is_alphanumeric() {
+                    RedosAtom::Any
+                } else {
+                    RedosAtom::Literal(e)
+                }
+            }),
+            '[' => {
+                skip_class(&chars, &mut i);
+                Some(RedosAtom::Any)
+            }
+            '(' => {
+                if opens_group(&chars, &mut i) {
+                    groups.push(RedosGroup::new(at));
+                }
+                None
+            }
+            ')' => {
+                if let Some(group) = groups.pop() {
+                    let parent = groups.last_mut().unwrap_or(&mut top);
+                    parent.unbounded_inside |= group.unbounded_inside;
+                    parent.record(group.first());
+                    closed = Some(group);
+                }
+                None
+            }
+            '|' => {
+                groups
+                    .last_mut()
+                    .unwrap_or(&mut top)
+                    .branch_firsts
+                    .push(None);
+                None
+            }
+            '^' | '$' => None,
+            '.' => Some(RedosAtom::Any),
+            c => Some(RedosAtom::Literal(c)),
+        };
+
+        if let Some(atom) = atom {
+            groups.last_mut().unwrap_or(&mut top).record(atom);
+        }
+    }
+
+    None
+}
+
+/// Read the quantifier starting with `c`, if it is one, returning whether it is unbounded.
+///
+/// `i` is the index of the character after `c`, and is moved past a `{n,m}` quantifier.
+fn quantifier(chars: &[(usize, char)], i: &mut usize, c: char) -> Option<bool> {
+    match c {
+        '*' | '+' => Some(true),
+        '?' => Some(false),
+        '{' => {
+            let rest = &chars[*i..];
+            let body = &rest[..rest.iter().position(|&(_, c)| c == '}')?];
+            if !body.first().is_some_and(|&(_, c)| c.is_ascii_digit()) {
+                return None;
+            }
+            *i += body.len() + 1;
+            Some(body.last().is_some_and(|&(_, c)| c == ','))
+        }
+        _ => None,
+    }
+}
+
+/// Skip a character class whose `[` was just read, a leading `]` is part of it.
+fn skip_class(chars: &[(usize, char)], i: &mut usize) {
+    let mut first = true;
+    while let Some(&(_, c)) = chars.get(*i) {
+        *i += 1;
+        match c {
+            '\\' => *i += 1,
+            ']' if !first => break,
+            _ => {}
+        }
+        first = c == '^' && first;
+    }
+}
+
+/// Skip the prefix of a group whose `(` was just read, such as `?:`, `?<name>` or `?i)`.
+///
+/// Returns `false` for an option setting such as `(?i)`, which is not a group.
+fn opens_group(chars: &[(usize, char)], i: &mut usize) -> bool {
+    if chars.get(*i).map(|&(_, c)| c) != Some('?') {
+        return true;
+    }
+
+    while let Some(&(_, c)) = chars.get(*i) {
+        *i += 1;
+        if c == ')' {
+            return false;
+        }
+        if matches!(c, ':' | '=' | '!' | '>' | '|') {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// What the first character of a branch may be, see [`redos_risk`].
+#[derive(Debug, Clone, Copy)]
+enum RedosAtom {
+    Literal(char),
+    Any,
+}
+
+/// A group being scanned by [`redos_risk`].
+#[derive(Debug)]
+struct RedosGroup {
+    // the byte offset of the opening `(`, 0 for the pattern itself
+    start: usize,
+    unbounded_inside: bool,
+    // the first atom of each branch, `None` while the branch is empty
+    branch_firsts: Vec<Option<RedosAtom>>,
+}
+
+impl RedosGroup {
+    fn new(start: usize) -> Self {
+        Self {
+            start,
+            unbounded_inside: false,
+            branch_firsts: vec![None],
+        }
+    }
+
+    /// Record an atom of the current branch, keeping it if it is the branch's first.
+    fn record(&mut self, atom: RedosAtom) {
+        if let Some(first @ None) = self.branch_firsts.last_mut() {
+            *first = Some(atom);
+        }
+    }
+
+    /// Returns what the group as a whole may start with.
+    fn first(&self) -> RedosAtom {
+        match self.branch_firsts.as_slice() {
+            [Some(atom)] => *atom,
+            _ => RedosAtom::Any,
+        }
+    }
+
+    /// Returns `true` if two branches may start with the same character.
+    fn overlapping_branches(&self) -> bool {
+        let firsts: Vec<_> = self.branch_firsts.iter().flatten().collect();
+
+        firsts.iter().enumerate().any(|(i, a)| {
+            firsts[i + 1..].iter().any(|b| match (a, b) {
+                (RedosAtom::Literal(a), RedosAtom::Literal(b)) => {
+                    a.to_lowercase().eq(b.to_lowercase())
+                }
+                _ => true,
+            })
+        })
+    }
+}
+
+/// Split a pattern on its top-level `|`, ignoring those in groups, classes or escaped.
+fn split_branches(pattern: &str) -> Vec<&str> {
+    let mut branches = Vec::new();
+    let (mut depth, mut start) = (0usize, 0);
+    let (mut escaped, mut in_class) = (false, false);
+
+    for (i, c) in pattern.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ']' if in_class => in_class = false,
+            _ if in_class => {}
+            '[' => in_class = true,
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            '|' if depth == 0 => {
+                branches.push(&pattern[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    branches.push(&pattern[start..]);
+    branches
+}
+
+/// Returns the length, in characters, of the longest run of literal characters in a pattern.
+fn longest_literal_run(pattern: &str) -> usize {
+    let mut chars = pattern.chars().peekable();
+    let (mut longest, mut run) = (0usize, 0usize);
+    let mut lookaround_depth = 0;
+    let mut depths = Vec::new();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(e) if !e.is_alphanumeric() && lookaround_depth == 0 => run += 1,
+                _ => run = 0,
+            },
+            '[' => {
+                // skip the class, a leading `]` is part of it
+                let mut first = true;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        ']' if !first => break,
+                        _ => {}
+                    }
+                    first = c == '^' && first;
+                }
+                run = 0;
+            }
+            '(' => {
+                let mut lookaround = false;
+
+                if chars.peek() == Some(&'?') {
+                    // skip the group prefix such as `?:`, `?<!` or `?i)`
+                    for c in chars.by_ref() {
+                        lookaround |= c == '=' || c == '!';
+                        if matches!(c, ':' | '=' | '!' | '>' | ')') {
+                            break;
+                        }
+                    }
+                }
+
+                lookaround_depth += usize::from(lookaround);
+                depths.push(lookaround);
+                run = 0;
+            }
+            ')' => {
+                lookaround_depth -= usize::from(depths.pop().unwrap_or(false));
+                run = 0;
+            }
+            '{' if chars.peek().is_some_and(char::is_ascii_digit) => {
+                // a `{n,m}` quantifier, the preceding literal is repeated
+                while chars.next().is_some_and(|c| c != '}') {}
+                run = run.saturating_sub(1);
+                longest = longest.max(run);
+                run = 0;
+            }
+            '?' | '*' => {
+                // the preceding literal is optional, it doesn't count
+                run = run.saturating_sub(1);
+                longest = longest.max(run);
+                run = 0;
+            }
+            '.' | '^' | '$' | '|' | '+' => run = 0,
+            _ if lookaround_depth == 0 => run += 1,
+            _ => {}
+        }
+
+        longest = longest.max(run);
+    }
+
+    longest
+}
+
+#[cfg(test)]
+mod features {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_read_entries_with_bom() {
+        let file = NamedTempFile::new().expect("Failed to create temp file");
+        fs::write(file.path(), "\u{feff}[\"Googlebot\", \"bingbot\"]\r\n\n")
+            .expect("failed to write to temp file");
+
+        let patterns = read_patterns(file.path().to_str().unwrap()).unwrap();
+
+        assert_eq!(
+            patterns,
+            vec!["Googlebot".to_string(), "bingbot".to_string()]
+        );
+        assert!(generate_pattern(file.path().to_str().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_read_entries_from() {
+        let crawlers = NamedTempFile::new().expect("Failed to create temp file");
+        let scrapers = NamedTempFile::new().expect("Failed to create temp file");
+        fs::write(crawlers.path(), r#"["googlebot", "bingbot"]"#).unwrap();
+        fs::write(scrapers.path(), r#"["scrapy", "googlebot"]"#).unwrap();
+
+        let entries = read_entries_from(&[
+            crawlers.path().to_str().unwrap(),
+            scrapers.path().to_str().unwrap(),
+        ])
+        .unwrap();
+        let patterns: Vec<&str> = entries.iter().map(|e| e.pattern.as_str()).collect();
+
+        assert_eq!(patterns, vec!["googlebot", "bingbot", "scrapy"]);
+
+        let error =
+            read_entries_from(&[crawlers.path().to_str().unwrap(), "missing.json"]).unwrap_err();
+
+        assert!(
+            matches!(error, BotDetectorError::InFile { ref path, .. } if path == "missing.json")
+        );
+    }
+
+    #[test]
+    fn test_read_entries_from_dir() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        fs::write(
+            dir.path().join("b-scrapers.json"),
+            r#"["scrapy", "googlebot"]"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("a-crawlers.json"),
+            r#"["googlebot", "bingbot"]"#,
+        )
+        .unwrap();
+        fs::write(dir.path().join("notes.txt"), "not patterns").unwrap();
+        fs::create_dir(dir.path().join("vendor")).unwrap();
+        fs::write(dir.path().join("vendor/tools.json"), r#"["curl"]"#).unwrap();
+        let dir_path = dir.path().to_str().unwrap();
+
+        let patterns = |entries: Vec<PatternEntry>| -> Vec<String> {
+            entries.into_iter().map(|entry| entry.pattern).collect()
+        };
+
+        assert_eq!(
+            patterns(read_entries_from_dir(dir_path, false).unwrap()),
+            vec!["googlebot", "bingbot", "scrapy"]
+        );
+        assert_eq!(
+            patterns(read_entries_from_dir(dir_path, true).unwrap()),
+            vec!["googlebot", "bingbot", "scrapy", "curl"]
+        );
+
+        let broken = dir.path().join("c-broken.json");
+        fs::write(&broken, "[\"unterminated").unwrap();
+        let error = read_entries_from_dir(dir_path, false).unwrap_err();
+        assert!(
+            matches!(error, BotDetectorError::InFile { ref path, .. } if *path == broken.display().to_string())
+        );
+
+        let error = read_entries_from_dir("missing-dir", false).unwrap_err();
+        assert!(
+            matches!(error, BotDetectorError::InFile { ref path, .. } if path == "missing-dir")
+        );
+    }
+
+    #[test]
+    fn test_stable_id() {
+        let entries = parse_entries(
+            r#"[{ "pattern": "googlebot", "id": "google-1" }, "googlebot", "bingbot"]"#,
+        )
+        .unwrap();
+
+        assert_eq!(entries[0].stable_id(), "google-1");
+        // FNV-1a reference values, so the fallback never changes between versions
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(
+            entries[1].stable_id(),
+            format!("{:016x}", fnv1a(b"googlebot"))
+        );
+        assert_ne!(entries[1].stable_id(), entries[2].stable_id());
+
+        assert_eq!(
+            serde_json::to_string(&entries[0]).unwrap(),
+            r#"{"pattern":"googlebot","id":"google-1"}"#
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = NamedTempFile::new().expect("Failed to create temp file");
+        let new = NamedTempFile::new().expect("Failed to create temp file");
+        fs::write(old.path(), r#"["googlebot", "bingbot", "curl"]"#).unwrap();
+        fs::write(
+            new.path(),
+            r#"["scrapy", {"pattern": "googlebot", "name": "Googlebot"}, "curl", "wget"]"#,
+        )
+        .unwrap();
+
+        let changes = diff(old.path().to_str().unwrap(), new.path().to_str().unwrap()).unwrap();
+
+        assert_eq!(changes.added, vec!["scrapy", "wget"]);
+        assert_eq!(changes.removed, vec!["bingbot"]);
+        assert!(matches!(
+            diff(old.path().to_str().unwrap(), "missing.json"),
+            Err(BotDetectorError::InFile { .. })
+        ));
+    }
+
+    #[test]
+    fn test_bench_pattern() {
+        assert!(bench_pattern("googlebot", &["Googlebot/2.1", "curl/7.68.0"]).is_ok());
+        assert!(matches!(
+            bench_pattern("(unclosed", &["Googlebot/2.1"]),
+            Err(BotDetectorError::RegexCompile(_))
+        ));
+    }
+
+    #[test]
+    fn test_is_broad() {
+        for pattern in [
+            "a",
+            ".*",
+            "^.*",
+            "ab",
+            "googlebot|a",
+            "(?<!googlebot)x",
+            "[a-z]+",
+        ] {
+            assert!(is_broad(pattern), "{pattern} should be broad");
+        }
+
+        for pattern in [
+            "googlebot",
+            "^bot",
+            "^\\d+\\b",
+            "(?<! cu)bots?(?:\\b|_)",
+            "bit\\.ly/",
+            "googlebot.*",
+        ] {
+            assert!(!is_broad(pattern), "{pattern} should not be broad");
+        }
+
+        // "[ab]" is a branch of its own
+        assert!(is_broad("[ab]|googlebot"));
+    }
+
+    #[test]
+    fn test_redos_risk() {
+        for pattern in [
+            "(a+)+$",
+            "(?:a*b?)*",
+            "(x+x+){2,}y",
+            "(a|ab)*c",
+            "(?i:A|a)+",
+            "(\\w|\\d)+",
+            "((ab)+c)*",
+            "([a-z]+\\.)+",
+        ] {
+            assert!(redos_risk(pattern).is_some(), "{pattern} should be risky");
+        }
+
+        for pattern in [
+            "googlebot",
+            "(a+)",
+            "(a+){2}",
+            "(a+)?",
+            "(?:bot|crawler)+",
+            "(a|b)*",
+            "[(a+)]+",
+            "\\(a+\\)+",
+            "(?i)a+b+",
+            "(?<!cu)bots?",
+        ] {
+            assert!(
+                redos_risk(pattern).is_none(),
+                "{pattern} should not be risky"
+            );
+        }
+
+        assert_eq!(
+            redos_risk("crawl(a+)+er").unwrap(),
+            "nested unbounded quantifiers in `(a+)+`"
+        );
+
+        let report = validate_patterns(&["googlebot".to_string(), "bot(a+)+".to_string()]);
+        assert!(report.errors.is_empty());
+        assert_eq!(report.warnings.len(), 1);
+        assert_eq!(report.warnings[0].index, 1);
+    }
+
+    #[test]
+    fn test_schema_versions() {
+        let legacy = parse_entries(r#"["Googlebot", { "pattern": "curl", "name": "curl" }]"#);
+        let versioned = parse_entries(
+            r#" {
+                "schema": 1,
+                "patterns": ["Googlebot", { "pattern": "curl", "name": "curl" }]
+            }"#,
+        );
+        assert_eq!(legacy.unwrap(), versioned.unwrap());
+
+        assert!(matches!(
+            parse_entries(r#"{ "schema": 2, "entries": {} }"#),
+            Err(BotDetectorError::UnsupportedSchema {
+                schema: 2,
+                supported: SCHEMA_VERSION
+            })
+        ));
+        assert!(matches!(
+            parse_entries(r#"{ "patterns": ["Googlebot"] }"#),
+            Err(BotDetectorError::JsonParse(_))
+        ));
+        assert!(matches!(
+            parse_entries(r#"{ "schema": 1 }"#),
+            Err(BotDetectorError::JsonParse(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_patterns_lines() {
+        let file = NamedTempFile::new().expect("Failed to create temp file");
+        let text = "\u{feff}# crawlers\r\ngooglebot\r\n\n   \n  # indented comment\n\\#hashtag\n(?<! cu)bots?(?:\\b|_)";
+        fs::write(file.path(), text).expect("failed to write to temp file");
+
+        let patterns = read_patterns_lines(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(
+            patterns,
+            vec!["googlebot", "\\#hashtag", "(?<! cu)bots?(?:\\b|_)"]
+        );
+
+        assert!(matches!(
+            read_patterns_lines("missing.txt"),
+            Err(BotDetectorError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_entries_jsonc() {
+        let file = NamedTempFile::new().expect("Failed to create temp file");
+        let jsonc = r#"[
+            // plain crawlers
+            "googlebot", // inline comment after a pattern
+            /* a block comment */"(?<!(?:lib))http://",
+            "a/*b*/c"/* adjacent block comment */,
+            { "pattern": "bingbot", /* name follows */ "name": "Bingbot", "weight": 0.5 }
+        ]"#;
+        fs::write(file.path(), jsonc).expect("failed to write to temp file");
+
+        let entries = read_entries_jsonc(file.path().to_str().unwrap()).unwrap();
+        let patterns: Vec<&str> = entries.iter().map(|e| e.pattern.as_str()).collect();
+
+        assert_eq!(
+            patterns,
+            vec!["googlebot", "(?<!(?:lib))http://", "a/*b*/c", "bingbot"]
+        );
+        assert_eq!(entries[3].name, Some("Bingbot".to_string()));
+        assert!((entries[3].weight - 0.5).abs() < f32::EPSILON);
+        assert!((entries[0].weight - 1.0).abs() < f32::EPSILON);
+    }
+}
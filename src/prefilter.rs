@@ -0,0 +1,481 @@
+//! Prefilter Module
+//!
+//! This module computes, for a set of patterns, which bytes a match can start with. A user
+//! agent that contains none of those bytes cannot match, so it can be rejected without running
+//! the regex engine. The analysis is conservative: any construct it doesn't understand disables
+//! the prefilter rather than risk rejecting a user agent the regex would match.
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A set of bytes, one flag per byte value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ByteSet([bool; 256]);
+
+impl ByteSet {
+    const EMPTY: ByteSet = ByteSet([false; 256]);
+    const ALL: ByteSet = ByteSet([true; 256]);
+
+    fn insert(&mut self, byte: u8) {
+        self.0[usize::from(byte)] = true;
+    }
+
+    fn union(&mut self, other: &ByteSet) {
+        for (flag, other) in self.0.iter_mut().zip(other.0) {
+            *flag |= other;
+        }
+    }
+
+    fn complement(&self) -> ByteSet {
+        let mut complement = *self;
+        for flag in &mut complement.0 {
+            *flag = !*flag;
+        }
+        complement
+    }
+
+    fn contains(&self, byte: u8) -> bool {
+        self.0[usize::from(byte)]
+    }
+
+    fn from_fn(f: impl Fn(u8) -> bool) -> ByteSet {
+        let mut set = ByteSet::EMPTY;
+        for byte in 0..=u8::MAX {
+            if f(byte) {
+                set.insert(byte);
+            }
+        }
+        set
+    }
+}
+
+/// The first bytes a match of the pattern set can start with.
+#[derive(Debug, Clone)]
+pub(crate) struct Prefilter {
+    /// First bytes of branches anchored with `^`, which can only match at the start.
+    anchored: ByteSet,
+
+    /// First bytes of the other branches, which can match anywhere.
+    floating: ByteSet,
+}
+
+impl Prefilter {
+    /// Analyse the patterns, returning `None` if any of them is beyond the analysis.
+    ///
+    /// `caseless` must reflect how the patterns are compiled. Patterns compiled in multi-line
+    /// mode are not supported, since `^` then also matches after a newline.
+    pub(crate) fn build<'p>(
+        patterns: impl IntoIterator<Item = &'p str>,
+        caseless: bool,
+    ) -> Option<Prefilter> {
+        let mut prefilter = Prefilter {
+            anchored: ByteSet::EMPTY,
+            floating: ByteSet::EMPTY,
+        };
+
+        for pattern in patterns {
+            let mut parser = Parser {
+                chars: pattern.chars().peekable(),
+                caseless,
+            };
+
+            loop {
+                let branch = parser.sequence()?;
+
+                // a pattern matching the empty string matches every user agent
+                if branch.nullable {
+                    return None;
+                }
+
+                if branch.anchored {
+                    prefilter.anchored.union(&branch.first);
+                } else {
+                    prefilter.floating.union(&branch.first);
+                }
+
+                match parser.chars.next() {
+                    None => break,
+                    Some('|') => {}
+                    Some(_) => return None,
+                }
+            }
+        }
+
+        Some(prefilter)
+    }
+
+    /// Returns `false` if the subject cannot possibly match any of the patterns.
+    pub(crate) fn may_match(&self, subject: &[u8]) -> bool {
+        subject
+            .first()
+            .is_some_and(|&byte| self.anchored.contains(byte))
+            || subject.iter().any(|&byte| self.floating.contains(byte))
+    }
+}
+
+/// The analysis of a sequence of atoms.
+struct Sequence {
+    /// Bytes the sequence can start with.
+    first: ByteSet,
+
+    /// Whether the sequence can match the empty string.
+    nullable: bool,
+
+    /// Whether the sequence starts with `^`.
+    anchored: bool,
+}
+
+/// The analysis of a single atom.
+struct Atom {
+    first: ByteSet,
+    nullable: bool,
+}
+
+impl Atom {
+    const ZERO_WIDTH: Atom = Atom {
+        first: ByteSet::EMPTY,
+        nullable: true,
+    };
+
+    fn of(first: ByteSet) -> Atom {
+        Atom {
+            first,
+            nullable: false,
+        }
+    }
+}
+
+struct Parser<'p> {
+    chars: Peekable<Chars<'p>>,
+    caseless: bool,
+}
+
+impl Parser<'_> {
+    /// Parse atoms up to the next `|`, `)` or the end of the pattern, which is not consumed.
+    fn sequence(&mut self) -> Option<Sequence> {
+        let mut sequence = Sequence {
+            first: ByteSet::EMPTY,
+            nullable: true,
+            anchored: self.chars.peek() == Some(&'^'),
+        };
+
+        while let Some(&c) = self.chars.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+
+            self.chars.next();
+            let mut atom = self.atom(c)?;
+            atom.nullable |= self.quantifier()?;
+
+            // once a consuming atom is met, later atoms can't start the match
+            if sequence.nullable {
+                sequence.first.union(&atom.first);
+                sequence.nullable = atom.nullable;
+            }
+        }
+
+        Some(sequence)
+    }
+
+    /// Parse the alternatives of a group, up to and including its closing `)`.
+    fn group(&mut self) -> Option<Atom> {
+        let mut group = Atom {
+            first: ByteSet::EMPTY,
+            nullable: false,
+        };
+
+        loop {
+            let branch = self.sequence()?;
+            group.first.union(&branch.first);
+            group.nullable |= branch.nullable;
+
+            match self.chars.next()? {
+                '|' => {}
+                ')' => return Some(group),
+                _ => return None,
+            }
+        }
+    }
+
+    /// Parse the atom starting with `c`, which was already consumed.
+    fn atom(&mut self, c: char) -> Option<Atom> {
+        match c {
+            '^' | '$' => Some(Atom::ZERO_WIDTH),
+            '.' => Some(Atom::of(ByteSet::ALL)),
+            '\\' => self.escape(),
+            '[' => self.class().map(Atom::of),
+            '(' => self.group_start(),
+            '*' | '+' | '?' => None,
+            '{' if self
+                .chars
+                .peek()
+                .is_some_and(|&c| c.is_ascii_digit() || c == ',') =>
+            {
+                None
+            }
+            c => Some(Atom::of(self.literal(c))),
+        }
+    }
+
+    /// Parse what follows a `(`.
+    fn group_start(&mut self) -> Option<Atom> {
+        if self.chars.peek() != Some(&'?') {
+            return self.group();
+        }
+
+        self.chars.next();
+
+        match self.chars.next()? {
+            ':' => self.group(),
+            '=' | '!' => self.group().map(|_| Atom::ZERO_WIDTH),
+            '<' => match self.chars.next()? {
+                '=' | '!' => self.group().map(|_| Atom::ZERO_WIDTH),
+                c if c.is_ascii_alphabetic() || c == '_' => {
+                    // a named group
+                    while self.chars.next()? != '>' {}
+                    self.group()
+                }
+                _ => None,
+            },
+            // option settings, atomic groups, comments...
+            _ => None,
+        }
+    }
+
+    /// Parse an optional quantifier, returning whether it allows zero repetitions.
+    fn quantifier(&mut self) -> Option<bool> {
+        let zero = match self.chars.peek() {
+            Some('?' | '*') => {
+                self.chars.next();
+                true
+            }
+            Some('+') => {
+                self.chars.next();
+                false
+            }
+            Some('{') => {
+                let mut lookahead = self.chars.clone();
+                lookahead.next();
+
+                match lookahead.peek() {
+                    // `{,n}` is a quantifier in recent pcre2 versions only
+                    Some(',') => return None,
+                    Some(c) if c.is_ascii_digit() => {}
+                    _ => return Some(false),
+                }
+
+                self.chars.next();
+                let mut min = String::new();
+
+                loop {
+                    match self.chars.next()? {
+                        '}' => break,
+                        ',' => {
+                            while self.chars.next()? != '}' {}
+                            break;
+                        }
+                        c => min.push(c),
+                    }
+                }
+
+                min.parse::<u32>().ok()? == 0
+            }
+            _ => return Some(false),
+        };
+
+        // lazy or possessive suffix
+        if matches!(self.chars.peek(), Some('?' | '+')) {
+            self.chars.next();
+        }
+
+        Some(zero)
+    }
+
+    /// Parse an escape sequence, the `\` being already consumed.
+    fn escape(&mut self) -> Option<Atom> {
+        match self.chars.next()? {
+            'b' | 'B' | 'A' | 'z' | 'Z' | 'G' => Some(Atom::ZERO_WIDTH),
+            e => self.escape_set(e).map(Atom::of),
+        }
+    }
+
+    /// Returns the bytes matched by the escape `\e`, outside of zero-width assertions.
+    fn escape_set(&self, e: char) -> Option<ByteSet> {
+        let digit = ByteSet::from_fn(|b| b.is_ascii_digit());
+        let word = ByteSet::from_fn(|b| b.is_ascii_alphanumeric() || b == b'_');
+        let space = ByteSet::from_fn(|b| matches!(b, b' ' | b'\t' | b'\n' | 0x0b | 0x0c | b'\r'));
+
+        match e {
+            'd' => Some(digit),
+            'D' => Some(digit.complement()),
+            'w' => Some(word),
+            'W' => Some(word.complement()),
+            's' => Some(space),
+            'S' => Some(space.complement()),
+            't' => Some(self.literal('\t')),
+            'n' => Some(self.literal('\n')),
+            'r' => Some(self.literal('\r')),
+            'f' => Some(self.literal('\x0c')),
+            'e' => Some(self.literal('\x1b')),
+            'a' => Some(self.literal('\x07')),
+            e if !e.is_ascii_alphanumeric() => Some(self.literal(e)),
+            // back references, unicode properties, hex escapes...
+            _ => None,
+        }
+    }
+
+    /// Parse a character class, the `[` being already consumed.
+    fn class(&mut self) -> Option<ByteSet> {
+        let negated = self.chars.peek() == Some(&'^');
+        if negated {
+            self.chars.next();
+        }
+
+        let mut set = ByteSet::EMPTY;
+        let mut first = true;
+        let mut previous: Option<char> = None;
+        let mut non_ascii = false;
+
+        loop {
+            let c = self.chars.next()?;
+            non_ascii |= !c.is_ascii();
+
+            match c {
+                ']' if !first => break,
+                '[' if self.chars.peek() == Some(&':') => return None,
+                '\\' => {
+                    let e = self.chars.next()?;
+                    non_ascii |= !e.is_ascii();
+                    set.union(&self.escape_set(e)?);
+                    previous = (!e.is_ascii_alphanumeric()).then_some(e);
+                }
+                '-' if previous.is_some() && self.chars.peek().is_some_and(|&c| c != ']') => {
+                    let start = previous.take()?;
+                    let end = match self.chars.next()? {
+                        '\\' => self.chars.next().filter(|e| !e.is_ascii_alphanumeric())?,
+                        end => end,
+                    };
+                    non_ascii |= !end.is_ascii();
+
+                    if !start.is_ascii() || !end.is_ascii() || start > end {
+                        return None;
+                    }
+
+                    for c in start..=end {
+                        set.union(&self.literal(c));
+                    }
+                }
+                c => {
+                    set.union(&self.literal(c));
+                    previous = Some(c);
+                }
+            }
+
+            first = false;
+        }
+
+        // a non-ASCII member allows every lead byte, its complement would reject them all
+        if negated && non_ascii {
+            return None;
+        }
+
+        Some(if negated { set.complement() } else { set })
+    }
+
+    /// Returns the bytes a literal character can start with.
+    fn literal(&self, c: char) -> ByteSet {
+        let mut set = ByteSet::EMPTY;
+
+        if let Some(byte) = u8::try_from(c).ok().filter(u8::is_ascii) {
+            set.insert(byte);
+
+            if self.caseless {
+                set.insert(byte.to_ascii_lowercase());
+                set.insert(byte.to_ascii_uppercase());
+            }
+        } else {
+            // case folding of non-ASCII characters is left to pcre2, allow any lead byte
+            for byte in 0x80..=u8::MAX {
+                set.insert(byte);
+            }
+        }
+
+        set
+    }
+}
+
+#[cfg(test)]
+mod features {
+    use super::*;
+    use pcre2::bytes::RegexBuilder;
+
+    #[test]
+    fn test_prefilter_agrees_with_regex() {
+        let patterns = [
+            "^curl/",
+            "^(?:wget|python)",
+            r"\[bot\]",
+            "(?<! cu)bots?(?:\\b|_)",
+            "@[a-z][\\w-]+\\.",
+            r"\b\d{13}\b",
+            "x{0,2}yz",
+        ];
+        let user_agents = [
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) Chrome/91.0",
+            "curl/7.68.0",
+            "Curl/7.68.0",
+            "python-requests/2.25",
+            "crawler [bot]",
+            "Googlebot/2.1",
+            "cubot phone",
+            "contact@example.com",
+            "1234567890123",
+            "xyz",
+            "MOZILLA",
+        ];
+
+        let prefilter = Prefilter::build(patterns, true).unwrap();
+        let regex = RegexBuilder::new()
+            .caseless(true)
+            .build(&patterns.join("|"))
+            .unwrap();
+
+        for user_agent in user_agents {
+            let subject = user_agent.as_bytes();
+
+            if regex.is_match(subject).unwrap() {
+                assert!(prefilter.may_match(subject), "{user_agent} was rejected");
+            }
+        }
+
+        assert!(!prefilter.may_match(b"MOZILLA"));
+        assert!(prefilter.may_match(b"CURL/7.68.0"));
+
+        let regex = RegexBuilder::new().build("[^é]ü").unwrap();
+        assert!(regex.is_match("üü".as_bytes()).unwrap());
+        assert_ne!(
+            Prefilter::build(["[^é]ü"], true).map(|prefilter| prefilter.may_match("üü".as_bytes())),
+            Some(false),
+            "üü was rejected"
+        );
+    }
+
+    #[test]
+    fn test_prefilter_unsupported() {
+        for pattern in [
+            "(?i)bot",
+            "a*",
+            "^",
+            r"\p{Lu}",
+            "(a)\\1",
+            "[[:alpha:]]",
+            "[^é]",
+        ] {
+            assert!(
+                Prefilter::build([pattern], true).is_none(),
+                "{pattern} should disable the prefilter"
+            );
+        }
+    }
+}
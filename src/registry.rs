@@ -0,0 +1,216 @@
+//! Detector Registry Module
+//!
+//! This module provides [`DetectorRegistry`], which keeps one compiled detector per tenant for
+//! services hosting several pattern sets side by side.
+
+use crate::detector::BotDetector;
+use crate::errors::BotDetectorError;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// A cache of compiled detectors keyed by tenant id.
+///
+/// Each tenant's detector is loaded from its patterns file on first use and shared from then
+/// on. Once `capacity` tenants are cached, loading another one evicts the least recently used.
+///
+/// # Example
+///
+/// ```no_run
+/// # use botagent::DetectorRegistry;
+/// let registry = DetectorRegistry::new(100);
+/// let detector = registry.get_or_load("acme", "tenants/acme.json").unwrap();
+/// assert!(detector.is_bot("Googlebot/2.1"));
+/// ```
+#[derive(Debug)]
+pub struct DetectorRegistry {
+    capacity: usize,
+    state: Mutex<RegistryState>,
+}
+
+#[derive(Debug, Default)]
+struct RegistryState {
+    tenants: HashMap<String, CachedDetector>,
+    // incremented on every use, so the smallest `last_used` is the least recently used
+    uses: u64,
+}
+
+#[derive(Debug)]
+struct CachedDetector {
+    json_path: String,
+    detector: Arc<BotDetector>,
+    last_used: u64,
+}
+
+impl DetectorRegistry {
+    /// Create an empty registry holding at most `capacity` detectors.
+    ///
+    /// With a `capacity` of `0`, nothing is cached and every call loads the patterns file.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(RegistryState::default()),
+        }
+    }
+
+    /// Returns the detector cached for the tenant, loading it from `json_path` if there is none.
+    ///
+    /// The file is only read on the tenant's first use, or when `json_path` differs from the
+    /// path the cached detector was loaded from. The registry is not locked while the file is
+    /// loaded, so a slow load doesn't hold up other tenants.
+    ///
+    /// # Arguments
+    ///
+    /// * `tenant` - The id of the tenant.
+    /// * `json_path` - Path to the tenant's JSON file containing bot patterns.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BotDetectorError` if the file cannot be read or parsed, or if the patterns
+    /// cannot be compiled. Nothing is cached in that case.
+    pub fn get_or_load(
+        &self,
+        tenant: &str,
+        json_path: &str,
+    ) -> Result<Arc<BotDetector>, BotDetectorError> {
+        if let Some(detector) = self.lookup(tenant, Some(json_path)) {
+            return Ok(detector);
+        }
+
+        let detector = Arc::new(BotDetector::from_json_path(json_path)?);
+        self.insert(tenant, json_path, Arc::clone(&detector));
+
+        Ok(detector)
+    }
+
+    /// Returns the detector cached for the tenant, if any, without loading anything.
+    #[must_use]
+    pub fn get(&self, tenant: &str) -> Option<Arc<BotDetector>> {
+        self.lookup(tenant, None)
+    }
+
+    /// Remove the tenant's detector from the registry, returning it if it was cached.
+    pub fn remove(&self, tenant: &str) -> Option<Arc<BotDetector>> {
+        self.lock()
+            .tenants
+            .remove(tenant)
+            .map(|cached| cached.detector)
+    }
+
+    /// Returns the number of cached detectors.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lock().tenants.len()
+    }
+
+    /// Returns `true` if no detector is cached.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> MutexGuard<'_, RegistryState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns the tenant's detector, marking it as used, if it is cached and was loaded from
+    /// `json_path` when given.
+    fn lookup(&self, tenant: &str, json_path: Option<&str>) -> Option<Arc<BotDetector>> {
+        let mut state = self.lock();
+        state.uses += 1;
+        let uses = state.uses;
+
+        let cached = state.tenants.get_mut(tenant)?;
+        if json_path.is_some_and(|json_path| json_path != cached.json_path) {
+            return None;
+        }
+        cached.last_used = uses;
+
+        Some(Arc::clone(&cached.detector))
+    }
+
+    fn insert(&self, tenant: &str, json_path: &str, detector: Arc<BotDetector>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut state = self.lock();
+
+        if !state.tenants.contains_key(tenant) && state.tenants.len() >= self.capacity {
+            let least_recent = state
+                .tenants
+                .iter()
+                .min_by_key(|(_, cached)| cached.last_used)
+                .map(|(tenant, _)| tenant.clone());
+
+            if let Some(least_recent) = least_recent {
+                state.tenants.remove(&least_recent);
+            }
+        }
+
+        state.uses += 1;
+        let last_used = state.uses;
+        state.tenants.insert(
+            tenant.to_string(),
+            CachedDetector {
+                json_path: json_path.to_string(),
+                detector,
+                last_used,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod features {
+    use super::*;
+    use std::fs;
+    use tempfile::NamedTempFile;
+
+    fn patterns_file(json: &str) -> NamedTempFile {
+        let file = NamedTempFile::new().unwrap();
+        fs::write(file.path(), json).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_get_or_load() {
+        let google = patterns_file(r#"["Googlebot"]"#);
+        let curl = patterns_file(r#"["curl"]"#);
+        let wget = patterns_file(r#"["wget"]"#);
+        let path = |file: &NamedTempFile| file.path().to_str().unwrap().to_string();
+        let registry = DetectorRegistry::new(2);
+
+        let a = registry.get_or_load("a", &path(&google)).unwrap();
+        assert!(a.is_bot("Googlebot/2.1"));
+        assert!(Arc::ptr_eq(
+            &a,
+            &registry.get_or_load("a", &path(&google)).unwrap()
+        ));
+
+        // a different path reloads the tenant
+        let a = registry.get_or_load("a", &path(&curl)).unwrap();
+        assert!(a.is_bot("curl/7.68.0"));
+        assert_eq!(registry.len(), 1);
+
+        registry.get_or_load("b", &path(&google)).unwrap();
+        assert!(registry.get("a").is_some());
+
+        // `b` is now the least recently used and is evicted
+        registry.get_or_load("c", &path(&wget)).unwrap();
+        assert_eq!(registry.len(), 2);
+        assert!(registry.get("b").is_none());
+        assert!(registry.get("a").is_some());
+
+        assert!(registry.get_or_load("d", "missing.json").is_err());
+        assert!(registry.get("d").is_none());
+
+        assert!(registry.remove("a").is_some());
+        assert!(registry.remove("a").is_none());
+        assert_eq!(registry.len(), 1);
+
+        let uncached = DetectorRegistry::new(0);
+        assert!(uncached.get_or_load("a", &path(&google)).is_ok());
+        assert!(uncached.is_empty());
+    }
+}
@@ -0,0 +1,304 @@
+//! Hot Reload Module
+//!
+//! This module keeps a detector in sync with its patterns file, reloading it in the background
+//! whenever the file changes.
+//!
+//! The file's directory is watched rather than the file itself, so editors and deployment tools
+//! that save by writing a new file and renaming it over the old one are picked up like in-place
+//! writes.
+
+use crate::detector::BotDetector;
+use crate::errors::BotDetectorError;
+use arc_swap::ArcSwap;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::ffi::OsString;
+use std::io;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// A detector reloaded from its patterns file whenever the file changes, see
+/// [`BotDetector::watch`].
+///
+/// Clones share the same detector.
+#[derive(Debug, Clone)]
+pub struct WatchedDetector {
+    current: Arc<ArcSwap<BotDetector>>,
+}
+
+impl WatchedDetector {
+    /// Returns the detector compiled from the latest valid version of the patterns file.
+    ///
+    /// The returned detector is not affected by later reloads, so a batch of checks made with
+    /// it sees a single pattern set.
+    #[must_use]
+    pub fn current(&self) -> Arc<BotDetector> {
+        self.current.load_full()
+    }
+
+    /// Check if the given user agent matches the current detector, see [`BotDetector::is_bot`].
+    #[must_use]
+    pub fn is_bot(&self, user_agent: &str) -> bool {
+        self.current.load().is_bot(user_agent)
+    }
+
+    fn replace(&self, detector: BotDetector) {
+        self.current.store(Arc::new(detector));
+    }
+}
+
+/// The file watcher of a patterns file and the thread reloading it. Dropping the handle stops
+/// both.
+#[derive(Debug)]
+pub struct WatchHandle {
+    watcher: Option<RecommendedWatcher>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        // dropping the watcher closes the channel, which ends the reloading thread
+        drop(self.watcher.take());
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl BotDetector {
+    /// Build a detector from a JSON file and reload it whenever the file changes.
+    ///
+    /// Changes are reported by the platform's file system notifications through `notify`, and
+    /// the file is reloaded by a background thread until the returned [`WatchHandle`] is
+    /// dropped. When a reload fails, e.g. because the file is being rewritten or contains an
+    /// invalid pattern, the failure is logged as a warning and the previous detector is kept
+    /// until the next change.
+    ///
+    /// Only available with the `watch` feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `json_path` - Path to the JSON file containing bot patterns.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BotDetectorError` if the initial load fails or the file can't be watched.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use botagent::BotDetector;
+    /// let (detector, _handle) = BotDetector::watch("patterns.json").unwrap();
+    /// assert!(detector.is_bot("Googlebot/2.1"));
+    /// ```
+    pub fn watch(json_path: &str) -> Result<(WatchedDetector, WatchHandle), BotDetectorError> {
+        Self::spawn_watch(json_path, Duration::ZERO)
+    }
+
+    /// Same as [`BotDetector::watch`], reloading only once the file has stopped changing for
+    /// `debounce`.
+    ///
+    /// Every change event restarts the debounce window, so a tool rewriting the file in several
+    /// small writes triggers a single reload, `debounce` after its last write. Meanwhile the
+    /// previous detector is kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `json_path` - Path to the JSON file containing bot patterns.
+    /// * `debounce` - How long the file must stay unchanged before it is reloaded.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BotDetectorError` if the initial load fails or the file can't be watched.
+    pub fn watch_debounced(
+        json_path: &str,
+        debounce: Duration,
+    ) -> Result<(WatchedDetector, WatchHandle), BotDetectorError> {
+        Self::spawn_watch(json_path, debounce)
+    }
+
+    fn spawn_watch(
+        json_path: &str,
+        debounce: Duration,
+    ) -> Result<(WatchedDetector, WatchHandle), BotDetectorError> {
+        let watched = WatchedDetector {
+            current: Arc::new(ArcSwap::from_pointee(Self::from_json_path(json_path)?)),
+        };
+
+        let path = Path::new(json_path);
+        let file_name = path
+            .file_name()
+            .map(OsString::from)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a path to a file"))?;
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+
+        let (sender, events) = mpsc::channel();
+        let mut notifier = notify::recommended_watcher(sender)?;
+        notifier.watch(directory, RecursiveMode::NonRecursive)?;
+
+        let thread = {
+            let watched = watched.clone();
+            let state = WatchState {
+                json_path: json_path.to_string(),
+                file_name,
+                debounce,
+            };
+
+            thread::spawn(move || state.run(&events, &watched))
+        };
+
+        let handle = WatchHandle {
+            watcher: Some(notifier),
+            thread: Some(thread),
+        };
+
+        Ok((watched, handle))
+    }
+}
+
+/// What the reloading thread knows about the patterns file.
+struct WatchState {
+    json_path: String,
+    file_name: OsString,
+    debounce: Duration,
+}
+
+impl WatchState {
+    /// Reload the detector after every change to the file, until the watcher is dropped.
+    fn run(&self, events: &Receiver<notify::Result<Event>>, watched: &WatchedDetector) {
+        while let Ok(event) = events.recv() {
+            if !self.is_change(&event) {
+                continue;
+            }
+
+            // any further change restarts the debounce window
+            let mut deadline = Instant::now() + self.debounce;
+            loop {
+                match events.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Ok(event) if self.is_change(&event) => {
+                        deadline = Instant::now() + self.debounce;
+                    }
+                    Ok(_) => {}
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+
+            self.reload(watched);
+        }
+    }
+
+    /// Returns whether the event reports a change to the contents or name of the file, so
+    /// reading it, including for a reload, doesn't trigger a reload.
+    fn is_change(&self, event: &notify::Result<Event>) -> bool {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                log::warn!("Failed to watch {} for changes: {e}", self.json_path);
+                return false;
+            }
+        };
+
+        matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+            && event
+                .paths
+                .iter()
+                .any(|path| path.file_name() == Some(self.file_name.as_os_str()))
+    }
+
+    fn reload(&self, watched: &WatchedDetector) {
+        let json_path = &self.json_path;
+
+        match BotDetector::from_json_path(json_path) {
+            Ok(detector) => watched.replace(detector),
+            Err(e) => log::warn!(
+                "Failed to reload {json_path}, keeping the previous patterns: {}",
+                e.error_message()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod features {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn wait_for(condition: impl Fn() -> bool) -> bool {
+        (0..200).any(|_| {
+            thread::sleep(Duration::from_millis(10));
+            condition()
+        })
+    }
+
+    fn patterns_file(json: &str) -> (TempDir, String) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("patterns.json");
+        fs::write(&path, json).unwrap();
+
+        (dir, path.to_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn test_watch() {
+        let (dir, path) = patterns_file(r#"["Googlebot"]"#);
+
+        let (detector, handle) = BotDetector::watch(&path).unwrap();
+        assert!(detector.is_bot("Googlebot/2.1"));
+
+        fs::write(&path, r#"["curl"]"#).unwrap();
+        assert!(wait_for(|| detector.is_bot("curl/7.68.0")));
+        assert!(!detector.is_bot("Googlebot/2.1"));
+
+        // a file saved by renaming a new version over it is picked up too
+        let replacement = dir.path().join("patterns.json.tmp");
+        fs::write(&replacement, r#"["python"]"#).unwrap();
+        fs::rename(&replacement, &path).unwrap();
+        assert!(wait_for(|| detector.is_bot("python-requests/2.25")));
+
+        // an invalid version keeps the previous detector
+        fs::write(&path, r#"["(unclosed"]"#).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        assert!(detector.is_bot("python-requests/2.25"));
+
+        // other files of the directory are ignored
+        fs::write(dir.path().join("other.json"), r#"["wget"]"#).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        assert!(detector.is_bot("python-requests/2.25"));
+
+        drop(handle);
+        fs::write(&path, r#"["wget"]"#).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        assert!(detector.is_bot("python-requests/2.25"));
+
+        assert!(BotDetector::watch("missing.json").is_err());
+    }
+
+    #[test]
+    fn test_watch_debounced() {
+        let (_dir, path) = patterns_file(r#"["Googlebot"]"#);
+
+        let (detector, _handle) =
+            BotDetector::watch_debounced(&path, Duration::from_millis(300)).unwrap();
+
+        // a burst of writes keeps the previous detector until it settles
+        for json in [r#"["wget"]"#, r#"["curl"#, r#"["curl"]"#] {
+            fs::write(&path, json).unwrap();
+            thread::sleep(Duration::from_millis(20));
+            assert!(detector.is_bot("Googlebot/2.1"));
+        }
+
+        assert!(wait_for(|| detector.is_bot("curl/7.68.0")));
+        assert!(!detector.is_bot("Wget/1.21"));
+        assert!(!detector.is_bot("Googlebot/2.1"));
+
+        assert!(BotDetector::watch_debounced("missing.json", Duration::ZERO).is_err());
+    }
+}
@@ -0,0 +1,25 @@
+//! `is_bot_env` reads the process environment, so its test runs in a binary of its own: setting
+//! a variable while other tests' threads read the environment is a data race.
+
+use botagent::errors::BotDetectorError;
+use botagent::{is_bot_env, PATTERNS_ENV_VAR};
+use std::env;
+use std::fs;
+use tempfile::NamedTempFile;
+
+#[test]
+fn test_is_bot_env() {
+    let temp_file = NamedTempFile::new().unwrap();
+    fs::write(temp_file.path(), r#"["Googlebot"]"#).unwrap();
+
+    env::remove_var(PATTERNS_ENV_VAR);
+    assert!(matches!(
+        is_bot_env("Googlebot/2.1"),
+        Err(BotDetectorError::EnvVar { .. })
+    ));
+
+    env::set_var(PATTERNS_ENV_VAR, temp_file.path());
+    assert!(is_bot_env("Googlebot/2.1").unwrap());
+    assert!(!is_bot_env("Mozilla/5.0").unwrap());
+    env::remove_var(PATTERNS_ENV_VAR);
+}